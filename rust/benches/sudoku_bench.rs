@@ -1,6 +1,9 @@
 use criterion::{criterion_group, criterion_main, Criterion};
 use std::path::Path;
-use sudoku_solver::algorithm_x_solver::AlgorithmXSudokuSolver;
+use sudoku_solver::algorithm_x_solver::{
+    AlgorithmXSudokuSolver, AlgorithmXWorkspaceSolver, MatrixStrategy,
+};
+use sudoku_solver::backtracking_solver::BacktrackingSolver;
 use sudoku_solver::SudokuSolver;
 
 fn benchmark(c: &mut Criterion) {
@@ -12,9 +15,26 @@ fn benchmark(c: &mut Criterion) {
         .map(|line| sudoku_solver::sudoku::Sudoku::try_from_str(line).unwrap())
         .collect::<Vec<_>>();
 
-    let solver = AlgorithmXSudokuSolver::new();
+    // Compares building a sub-matrix per sudoku with covering the givens in the full matrix
+    let mut group = c.benchmark_group("solve sudokus");
+    for strategy in [MatrixStrategy::SubMatrix, MatrixStrategy::FullMatrix] {
+        let solver: Box<dyn SudokuSolver> = match strategy {
+            MatrixStrategy::SubMatrix => Box::new(AlgorithmXSudokuSolver::new()),
+            MatrixStrategy::FullMatrix => Box::new(AlgorithmXWorkspaceSolver::default()),
+        };
+        group.bench_function(strategy.to_string(), |b| {
+            b.iter(|| {
+                for sudoku in &sudokus {
+                    let _ = solver.solve(sudoku.clone()).unwrap();
+                }
+            })
+        });
+    }
+    group.finish();
 
-    c.bench_function("solve sudokus", |b| {
+    let solver = BacktrackingSolver::new();
+
+    c.bench_function("solve sudokus backtracking", |b| {
         b.iter(|| {
             for sudoku in &sudokus {
                 let _ = solver.solve(sudoku.clone()).unwrap();
@@ -1,4 +1,80 @@
-use super::algorithm_x_solver::NR_CONSTRAINTS;
+use std::{
+    fmt,
+    ops::ControlFlow,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+use thiserror::Error;
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// An unsigned integer type that can be used to address the nodes of a [`ExactCover`] grid.
+///
+/// Smaller types keep the grid compact and cache friendly, wider types allow for bigger
+/// matrices.
+///
+/// The grid is accessed without bounds checks, which relies on the conversions being
+/// lossless for every node index, so the trait is sealed. It is implemented for `u8`, `u16`,
+/// `u32`, `u64` and `usize`.
+pub trait Index: sealed::Sealed + Copy + Ord + Default + fmt::Debug {
+    const MAX: Self;
+
+    fn from_usize(value: usize) -> Self;
+    fn to_usize(self) -> usize;
+
+    /// The largest value as a `usize`, or `usize::MAX` if the type is wider.
+    fn max_usize() -> usize;
+}
+
+macro_rules! impl_index {
+    ($($t:ty),*) => {
+        $(
+            impl sealed::Sealed for $t {}
+
+            impl Index for $t {
+                const MAX: Self = <$t>::MAX;
+
+                // `ExactCover` checks that all node indices fit before building the grid
+                #[inline(always)]
+                fn from_usize(value: usize) -> Self {
+                    debug_assert!(value <= Self::max_usize());
+                    value as $t
+                }
+
+                #[inline(always)]
+                fn to_usize(self) -> usize {
+                    self as usize
+                }
+
+                #[inline(always)]
+                fn max_usize() -> usize {
+                    usize::try_from(<$t>::MAX).unwrap_or(usize::MAX)
+                }
+            }
+        )*
+    };
+}
+
+impl_index!(u8, u16, u32, u64, usize);
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ExactCoverError {
+    #[error("Column {col} in row {row} is out of range, the matrix has {n_cols} columns")]
+    ColumnOutOfRange {
+        row: usize,
+        col: usize,
+        n_cols: usize,
+    },
+    #[error("Column {col} appears more than once in row {row}")]
+    DuplicateColumn { row: usize, col: usize },
+    #[error("A matrix with {0} nodes can't be addressed with the chosen index type")]
+    IndexOverflow(usize),
+}
 
 struct UncheckedIndexVec<T>(Vec<T>);
 
@@ -8,28 +84,28 @@ impl<T: Default + Clone> UncheckedIndexVec<T> {
     }
 
     #[inline(always)]
-    fn get(&self, idx: u16) -> &T {
-        debug_assert!((idx as usize) < self.0.len());
-        unsafe { self.0.get_unchecked(idx as usize) }
+    fn get<I: Index>(&self, idx: I) -> &T {
+        debug_assert!(idx.to_usize() < self.0.len());
+        unsafe { self.0.get_unchecked(idx.to_usize()) }
     }
 
     #[inline(always)]
-    fn get_mut(&mut self, idx: u16) -> &mut T {
-        debug_assert!((idx as usize) < self.0.len());
-        unsafe { self.0.get_unchecked_mut(idx as usize) }
+    fn get_mut<I: Index>(&mut self, idx: I) -> &mut T {
+        debug_assert!(idx.to_usize() < self.0.len());
+        unsafe { self.0.get_unchecked_mut(idx.to_usize()) }
     }
 }
 
-struct NodeList {
-    left: UncheckedIndexVec<u16>,
-    right: UncheckedIndexVec<u16>,
-    up: UncheckedIndexVec<u16>,
-    down: UncheckedIndexVec<u16>,
-    row: UncheckedIndexVec<u16>,
-    col: UncheckedIndexVec<u16>,
+struct NodeList<I> {
+    left: UncheckedIndexVec<I>,
+    right: UncheckedIndexVec<I>,
+    up: UncheckedIndexVec<I>,
+    down: UncheckedIndexVec<I>,
+    row: UncheckedIndexVec<I>,
+    col: UncheckedIndexVec<I>,
 }
 
-impl NodeList {
+impl<I: Index> NodeList<I> {
     fn new(capacity: usize) -> Self {
         Self {
             left: UncheckedIndexVec::new(capacity),
@@ -42,108 +118,339 @@ impl NodeList {
     }
 
     #[inline(always)]
-    fn left(&self, idx: u16) -> u16 {
+    fn left(&self, idx: I) -> I {
         *self.left.get(idx)
     }
 
     #[inline(always)]
-    fn left_mut(&mut self, idx: u16) -> &mut u16 {
+    fn left_mut(&mut self, idx: I) -> &mut I {
         self.left.get_mut(idx)
     }
 
     #[inline(always)]
-    fn right(&self, idx: u16) -> u16 {
+    fn right(&self, idx: I) -> I {
         *self.right.get(idx)
     }
 
     #[inline(always)]
-    fn right_mut(&mut self, idx: u16) -> &mut u16 {
+    fn right_mut(&mut self, idx: I) -> &mut I {
         self.right.get_mut(idx)
     }
 
     #[inline(always)]
-    fn up(&self, idx: u16) -> u16 {
+    fn up(&self, idx: I) -> I {
         *self.up.get(idx)
     }
 
     #[inline(always)]
-    fn up_mut(&mut self, idx: u16) -> &mut u16 {
+    fn up_mut(&mut self, idx: I) -> &mut I {
         self.up.get_mut(idx)
     }
 
     #[inline(always)]
-    fn down(&self, idx: u16) -> u16 {
+    fn down(&self, idx: I) -> I {
         *self.down.get(idx)
     }
 
     #[inline(always)]
-    fn down_mut(&mut self, idx: u16) -> &mut u16 {
+    fn down_mut(&mut self, idx: I) -> &mut I {
         self.down.get_mut(idx)
     }
 
     #[inline(always)]
-    fn row(&self, idx: u16) -> u16 {
+    fn row(&self, idx: I) -> I {
         *self.row.get(idx)
     }
 
     #[inline(always)]
-    fn col(&self, idx: u16) -> u16 {
+    fn col(&self, idx: I) -> I {
         *self.col.get(idx)
     }
 }
 
-pub struct NodeGrid {
-    nodes: NodeList,
-    col_counts: [u8; NR_CONSTRAINTS + 1],
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SearchState {
+    // Nothing is covered, the search starts from scratch
+    Ready,
+    // The rows on the stack form a solution, the search continues by backtracking
+    Solution,
+    // All solutions have been found and the grid has been restored
+    Exhausted,
+    // The search exceeded one of its limits, the grid is restored by the next search
+    Interrupted(Interruption),
+}
+
+// How many iterations of the search pass between checks of the clock and cancellation token
+const CLOCK_CHECK_INTERVAL: u64 = 1024;
+
+/// Limits which stop a search before it is done, see [`ExactCover::set_limits`].
+#[derive(Debug, Clone, Default)]
+pub struct SearchLimits {
+    /// The search stops once this point in time has passed
+    pub deadline: Option<Instant>,
+    /// The search stops before placing more digits than this, see [`SearchMetrics::nodes`]
+    pub max_nodes: Option<u64>,
+    /// The search stops once this is set, e.g. from another thread
+    pub cancel: Option<Arc<AtomicBool>>,
+}
+
+impl SearchLimits {
+    /// Limits the search to end before the given duration has passed from now. Durations too
+    /// long to be represented as a point in time don't limit the search.
+    pub fn timeout(timeout: Duration) -> Self {
+        Self {
+            deadline: Instant::now().checked_add(timeout),
+            ..Self::default()
+        }
+    }
+
+    pub fn is_unlimited(&self) -> bool {
+        self.deadline.is_none() && self.max_nodes.is_none() && self.cancel.is_none()
+    }
+
+    // Checks the limits after `nodes` nodes. The deadline and the cancellation token are only
+    // checked with `check_clock`, as reading the clock on every node slows the search down.
+    pub(crate) fn check(&self, nodes: u64, check_clock: bool) -> Option<Interruption> {
+        if self.max_nodes.is_some_and(|max_nodes| nodes >= max_nodes) {
+            return Some(Interruption::NodeLimit);
+        }
+        if !check_clock {
+            return None;
+        }
+        if let Some(cancel) = &self.cancel {
+            if cancel.load(Ordering::Relaxed) {
+                return Some(Interruption::Cancelled);
+            }
+        }
+        if self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            return Some(Interruption::TimedOut);
+        }
+        None
+    }
+}
+
+/// A step of a search, as reported to a [`SearchObserver`]. Rows and columns are indices into
+/// the matrix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchEvent {
+    /// The column with the fewest rows was chosen to branch on, `count` is the number of rows
+    /// left in it
+    ChooseColumn { col: usize, count: usize },
+    /// A column was covered, removing all rows which have a one in it
+    CoverColumn { col: usize },
+    /// A row was added to the partial solution
+    SelectRow { row: usize },
+    /// A row was removed from the partial solution again
+    Backtrack { row: usize },
+    /// The rows of the partial solution cover all columns
+    Solution,
+}
+
+/// Watches the steps of a search, e.g. to trace or visualize it. The events are the
+/// [`SearchEvent`]s of an [`ExactCover`], or the
+/// [`TraceEvent`](crate::TraceEvent)s of the sudoku solvers. Closures taking an event are
+/// observers, and so are vectors, which keep every event.
+///
+/// Returning [`ControlFlow::Break`] stops the search, which then ends as
+/// [`Interruption::Cancelled`]. No more events are reported after that.
+pub trait SearchObserver<E = SearchEvent> {
+    fn on_event(&mut self, event: E) -> ControlFlow<()>;
+}
+
+impl<E, F: FnMut(E) -> ControlFlow<()>> SearchObserver<E> for F {
+    fn on_event(&mut self, event: E) -> ControlFlow<()> {
+        self(event)
+    }
+}
+
+impl<E> SearchObserver<E> for Vec<E> {
+    fn on_event(&mut self, event: E) -> ControlFlow<()> {
+        self.push(event);
+        ControlFlow::Continue(())
+    }
+}
+
+// Ignores all events, so that unobserved searches don't pay for them
+struct NoObserver;
+
+impl SearchObserver for NoObserver {
+    #[inline(always)]
+    fn on_event(&mut self, _event: SearchEvent) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+}
+
+/// Why a search was stopped before it was done.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum Interruption {
+    #[error("The deadline of the search has passed")]
+    TimedOut,
+    #[error("The search has used up its node budget")]
+    NodeLimit,
+    /// The cancellation token was set, or the observer stopped the search
+    #[error("The search was cancelled")]
+    Cancelled,
+}
+
+/// Describes the size of the search tree explored by the last search.
+///
+/// The fields mean the same for all solvers, so their numbers can be compared. A node is a
+/// digit placed into a cell, which is a row added to the partial solution in Algorithm X. The
+/// givens count as nodes as well, forced digits and guesses alike. Once a search has found a
+/// solution and stopped, the nodes which weren't taken back are the filled cells.
+///
+/// The counters are always collected by an [`ExactCover`], while the column selections and the
+/// times are only collected on request, see [`ExactCover::collect_details`], as timing every
+/// cover slows the search down.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchMetrics {
+    /// Number of digits that were placed, tentatively or not
+    pub nodes: u64,
+    /// Number of placed digits that were taken back again
+    pub backtracks: u64,
+    /// Maximum number of filled cells at any point of the search
+    pub max_depth: usize,
+    /// How often every column was chosen to branch on, empty unless details are collected. The
+    /// backtracking solver branches on cells, so its columns are the cells.
+    pub column_selections: Vec<u64>,
+    /// Time spent placing digits and removing the candidates they rule out, zero unless details
+    /// are collected. This is covering columns in Algorithm X, and filling in singles in the
+    /// backtracking solver.
+    pub cover_time: Duration,
+    /// Time spent taking back placed digits, zero unless details are collected. This is
+    /// uncovering columns in Algorithm X, and copying the grid before a guess in the
+    /// backtracking solver, as the copy is dropped to take the guess back.
+    pub uncover_time: Duration,
+}
+
+impl SearchMetrics {
+    /// Adds the metrics of another search to these, keeping the larger maximum depth.
+    pub fn add(&mut self, other: &SearchMetrics) {
+        self.nodes += other.nodes;
+        self.backtracks += other.backtracks;
+        self.max_depth = self.max_depth.max(other.max_depth);
+        if self.column_selections.len() < other.column_selections.len() {
+            self.column_selections
+                .resize(other.column_selections.len(), 0);
+        }
+        for (total, count) in self
+            .column_selections
+            .iter_mut()
+            .zip(&other.column_selections)
+        {
+            *total += count;
+        }
+        self.cover_time += other.cover_time;
+        self.uncover_time += other.uncover_time;
+    }
+}
+
+struct NodeGrid<I> {
+    nodes: NodeList<I>,
+    col_counts: UncheckedIndexVec<I>,
+    stack: Vec<(I, I)>, // (col_hdr, col_node)
+    // The number of selected rows at the bottom of the stack, which the search never undoes
+    n_selected: usize,
+    // Where the nodes of each row start, followed by the end of the last row
+    row_starts: Vec<usize>,
+    state: SearchState,
+    // Whether the observer asked to stop the search
+    stopped: bool,
+    metrics: SearchMetrics,
+    // Whether column selections and cover times are collected
+    details: bool,
+    limits: SearchLimits,
+    // The iterations of the current search, which tell when to check the clock
+    ticks: u64,
 }
 
-impl NodeGrid {
-    const ROOT: u16 = 0;
+impl<I: Index> NodeGrid<I> {
+    const ROOT: usize = 0;
+
+    #[inline(always)]
+    fn root() -> I {
+        I::from_usize(Self::ROOT)
+    }
 
-    pub fn from_sparse_matrix(sparse_mat: &[[u16; 4]], n_total_cols: usize) -> Self {
-        let n_rows = sparse_mat.len();
+    // The caller has to make sure that all column indices are in range, unique within a row
+    // and that all nodes can be addressed with `I`. The columns from `n_primary_cols` onwards
+    // are secondary columns.
+    fn from_sparse_matrix<R: AsRef<[I]>>(
+        sparse_mat: &[R],
+        n_primary_cols: usize,
+        n_total_cols: usize,
+    ) -> Self {
         let n_cols = n_total_cols;
         let n_hdr_nodes = n_cols + 1; // +1 for root node
+        let n_row_nodes = sparse_mat
+            .iter()
+            .map(|row| row.as_ref().len())
+            .sum::<usize>();
 
-        let mut nodes = NodeList::new(n_hdr_nodes + 4 * n_rows);
+        let mut nodes = NodeList::new(n_hdr_nodes + n_row_nodes);
 
         // Set up header nodes
         for i in 1..=n_cols {
-            let i = i as u16;
-            // Link header nodes in a circular doubly linked list
-            *nodes.left_mut(i) = i - 1;
-            *nodes.right_mut(i - 1) = i;
+            let prev = I::from_usize(i - 1);
+            let i = I::from_usize(i);
+            if i.to_usize() <= n_primary_cols {
+                // Link primary header nodes in a circular doubly linked list
+                *nodes.left_mut(i) = prev;
+                *nodes.right_mut(prev) = i;
+            } else {
+                // Secondary columns don't have to be covered, so they are never chosen by the
+                // search. Their headers only link to themselves.
+                *nodes.left_mut(i) = i;
+                *nodes.right_mut(i) = i;
+            }
 
             *nodes.up_mut(i) = i;
             *nodes.down_mut(i) = i;
             *nodes.col.get_mut(i) = i;
         }
 
-        *nodes.left_mut(0) = (n_hdr_nodes - 1) as u16;
+        *nodes.left_mut(Self::root()) = I::from_usize(n_primary_cols);
+        *nodes.right_mut(I::from_usize(n_primary_cols)) = Self::root();
 
         let mut grid = NodeGrid {
             nodes,
-            col_counts: [0; 325],
+            col_counts: UncheckedIndexVec::new(n_hdr_nodes),
+            stack: Vec::with_capacity(128),
+            n_selected: 0,
+            row_starts: Vec::with_capacity(sparse_mat.len() + 1),
+            state: SearchState::Ready,
+            stopped: false,
+            metrics: SearchMetrics::default(),
+            details: false,
+            limits: SearchLimits::default(),
+            ticks: 0,
         };
 
         // Convert sparse matrix into "grid"
-        let mut new_idx = n_hdr_nodes as u16;
+        let mut new_idx = n_hdr_nodes;
         for (row_idx, row) in sparse_mat.iter().enumerate() {
-            let row_idx = row_idx as u16;
+            let row_idx = I::from_usize(row_idx);
+            grid.row_starts.push(new_idx);
 
             let mut first_in_row = None;
-            for &col in row.iter() {
+            for &col in row.as_ref() {
                 grid.insert_new(new_idx, col, row_idx, &mut first_in_row);
                 new_idx += 1;
             }
         }
+        grid.row_starts.push(new_idx);
 
         grid
     }
 
     #[inline(always)]
-    fn insert_new(&mut self, idx: u16, hdr: u16, row_idx: u16, first_in_row: &mut Option<u16>) {
-        self.insert_above(idx, hdr + 1, row_idx);
+    fn insert_new(&mut self, idx: usize, hdr: I, row_idx: I, first_in_row: &mut Option<I>) {
+        let left_idx = I::from_usize(idx - 1);
+        let idx = I::from_usize(idx);
+        self.insert_above(idx, I::from_usize(hdr.to_usize() + 1), row_idx);
         match *first_in_row {
             None => {
                 *self.nodes.right_mut(idx) = idx;
@@ -151,7 +458,6 @@ impl NodeGrid {
                 *first_in_row = Some(idx);
             }
             Some(first_idx) => {
-                let left_idx = idx - 1;
                 *self.nodes.left_mut(idx) = left_idx;
                 *self.nodes.right_mut(idx) = first_idx;
 
@@ -162,7 +468,7 @@ impl NodeGrid {
     }
 
     #[inline(always)]
-    fn insert_above(&mut self, new_idx: u16, hdr: u16, row_idx: u16) {
+    fn insert_above(&mut self, new_idx: I, hdr: I, row_idx: I) {
         // Update the node above the header node to point to new node
         let above = self.nodes.up(hdr);
         *self.nodes.down_mut(above) = new_idx;
@@ -178,51 +484,56 @@ impl NodeGrid {
     }
 
     #[inline(always)]
-    fn count(&self, col_idx: u16) -> u8 {
-        unsafe { *self.col_counts.get_unchecked(col_idx as usize) }
-    }
-
-    #[inline(always)]
-    fn count_mut(&mut self, col_idx: u16) -> &mut u8 {
-        unsafe { self.col_counts.get_unchecked_mut(col_idx as usize) }
+    fn count(&self, col_idx: I) -> I {
+        *self.col_counts.get(col_idx)
     }
 
     #[inline(always)]
-    fn inc_count(&mut self, col_idx: u16) {
-        *self.count_mut(col_idx) += 1;
+    fn inc_count(&mut self, col_idx: I) {
+        let count = self.col_counts.get_mut(col_idx);
+        *count = I::from_usize(count.to_usize() + 1);
     }
 
     #[inline(always)]
-    fn dec_count(&mut self, col_idx: u16) {
-        *self.count_mut(col_idx) -= 1;
+    fn dec_count(&mut self, col_idx: I) {
+        let count = self.col_counts.get_mut(col_idx);
+        *count = I::from_usize(count.to_usize() - 1);
     }
 
     #[inline(always)]
-    fn choose_column(&self) -> u16 {
-        let mut min_count = u8::MAX;
-        let mut min_node = 0;
+    fn choose_column(&self) -> I {
+        let zero = I::default();
+        let mut min_count = I::MAX;
+        let mut min_node = Self::root();
 
-        let mut fwd_node = self.nodes.right(Self::ROOT);
-        let mut bwd_node = self.nodes.left(Self::ROOT);
-        while fwd_node < bwd_node && min_count != 0 {
+        // Scan the header list from both ends until the two cursors meet
+        let mut fwd_node = self.nodes.right(Self::root());
+        let mut bwd_node = self.nodes.left(Self::root());
+        loop {
             let count = self.count(fwd_node);
             if count < min_count {
                 min_count = count;
                 min_node = fwd_node;
             }
+            if fwd_node == bwd_node {
+                break;
+            }
             let count = self.count(bwd_node);
             if count < min_count {
                 min_count = count;
                 min_node = bwd_node;
             }
             fwd_node = self.nodes.right(fwd_node);
+            if fwd_node == bwd_node || min_count == zero {
+                break;
+            }
             bwd_node = self.nodes.left(bwd_node);
         }
         min_node
     }
 
     #[inline(always)]
-    fn cover_column(&mut self, col: u16) {
+    fn cover_column(&mut self, col: I) {
         // Remove the column header from the header list
         let left = self.nodes.left(col);
         let right = self.nodes.right(col);
@@ -249,7 +560,7 @@ impl NodeGrid {
     }
 
     #[inline(always)]
-    fn uncover_column(&mut self, col: u16) {
+    fn uncover_column(&mut self, col: I) {
         let left = self.nodes.left(col);
         let right = self.nodes.right(col);
 
@@ -273,18 +584,28 @@ impl NodeGrid {
         *self.nodes.right_mut(left) = col;
     }
 
+    // Reports an event, unless the observer already asked to stop
     #[inline(always)]
-    fn cover_row(&mut self, origin: u16) {
+    fn notify(&mut self, observer: &mut impl SearchObserver, event: SearchEvent) {
+        if !self.stopped && observer.on_event(event).is_break() {
+            self.stopped = true;
+        }
+    }
+
+    #[inline(always)]
+    fn cover_row(&mut self, origin: I, observer: &mut impl SearchObserver) {
         let mut row_node = self.nodes.right(origin);
         while row_node != origin {
             let row_node_col = self.nodes.col(row_node);
+            let col = row_node_col.to_usize() - 1;
+            self.notify(observer, SearchEvent::CoverColumn { col });
             self.cover_column(row_node_col);
             row_node = self.nodes.right(row_node);
         }
     }
 
     #[inline(always)]
-    fn uncover_row(&mut self, origin: u16) {
+    fn uncover_row(&mut self, origin: I) {
         let mut row_node = self.nodes.left(origin);
         while row_node != origin {
             let row_node_col = self.nodes.col(row_node);
@@ -320,47 +641,704 @@ impl NodeGrid {
     //     None
     // }
 
-    // A non recursive version of search
-    pub fn search(&mut self) -> Option<Vec<u16>> {
-        let mut stack: Vec<(u16, u16)> = Vec::with_capacity(128); // (col_hdr, col_node)
+    // A non recursive version of search, which can be resumed to find further solutions.
+    // Returns true if the rows on the stack form a new solution. Every step is reported to the
+    // observer, the search is compiled without any trace of it for `NoObserver`. The observer
+    // can only stop the search between steps, so the matrix stays consistent.
+    fn advance(&mut self, observer: &mut impl SearchObserver) -> bool {
+        match self.state {
+            SearchState::Ready => {}
+            SearchState::Solution => {
+                if !self.backtrack(observer) {
+                    self.state = SearchState::Exhausted;
+                    return false;
+                }
+            }
+            SearchState::Exhausted | SearchState::Interrupted(_) => return false,
+        }
 
+        let limited = !self.limits.is_unlimited();
         loop {
-            if self.nodes.right(Self::ROOT) == Self::ROOT {
-                let result = stack
-                    .into_iter()
-                    .map(|(_, col_node)| self.nodes.row(col_node))
-                    .collect();
-                return Some(result);
+            if self.stopped {
+                self.state = SearchState::Interrupted(Interruption::Cancelled);
+                return false;
+            }
+            if self.nodes.right(Self::root()) == Self::root() {
+                self.state = SearchState::Solution;
+                self.notify(observer, SearchEvent::Solution);
+                return true;
+            }
+
+            if limited {
+                let check_clock = self.ticks.is_multiple_of(CLOCK_CHECK_INTERVAL);
+                self.ticks += 1;
+                if let Some(interruption) = self.limits.check(self.metrics.nodes, check_clock) {
+                    self.state = SearchState::Interrupted(interruption);
+                    return false;
+                }
             }
 
             let col_hdr = self.choose_column();
+            let col = col_hdr.to_usize() - 1;
+            let count = self.count(col_hdr).to_usize();
+            self.notify(observer, SearchEvent::ChooseColumn { col, count });
+            self.notify(observer, SearchEvent::CoverColumn { col });
+            let timer = self.start_timer();
             self.cover_column(col_hdr);
+            if let Some(timer) = timer {
+                self.metrics.cover_time += timer.elapsed();
+                self.metrics.column_selections[col_hdr.to_usize() - 1] += 1;
+            }
 
             let col_node = self.nodes.down(col_hdr);
             if col_node != col_hdr {
                 // Found a row to cover
-                self.cover_row(col_node);
-                stack.push((col_hdr, col_node));
+                self.push_row(col_hdr, col_node, observer);
 
                 continue;
             }
 
             // Backtrack
+            let timer = self.start_timer();
             self.uncover_column(col_hdr);
-            while let Some((prev_col_hdr, prev_col_node)) = stack.pop() {
-                self.uncover_row(prev_col_node);
-                let next_col_node = self.nodes.down(prev_col_node);
-                if next_col_node != prev_col_hdr {
-                    // Found the next row to cover
-                    self.cover_row(next_col_node);
-                    stack.push((prev_col_hdr, next_col_node));
-                    break;
+            if let Some(timer) = timer {
+                self.metrics.uncover_time += timer.elapsed();
+            }
+            if !self.backtrack(observer) {
+                self.state = SearchState::Exhausted;
+                return false; // No more options to backtrack
+            }
+        }
+    }
+
+    // Replaces the contents of `solution` with the rows of the next solution
+    fn next_solution_into(
+        &mut self,
+        solution: &mut Vec<usize>,
+        observer: &mut impl SearchObserver,
+    ) -> bool {
+        solution.clear();
+        if !self.advance(observer) {
+            return false;
+        }
+        solution.extend(
+            self.stack
+                .iter()
+                .map(|&(_, col_node)| self.nodes.row(col_node).to_usize()),
+        );
+        true
+    }
+
+    fn count_solutions(&mut self, limit: usize) -> usize {
+        self.restart();
+        let mut count = 0;
+        while count < limit && self.advance(&mut NoObserver) {
+            count += 1;
+        }
+        // The counted solutions are undone, but the caller still has to learn about an
+        // interruption
+        let state = self.state;
+        self.rewind();
+        if let SearchState::Interrupted(_) = state {
+            self.state = state;
+        }
+        count
+    }
+
+    // Covers the given row and keeps it in all following solutions. Returns false if the row is
+    // empty or one of its columns is already covered.
+    fn select_row(&mut self, row: usize) -> bool {
+        self.rewind();
+        let (start, end) = (self.row_starts[row], self.row_starts[row + 1]);
+        if start == end {
+            return false;
+        }
+
+        // A covered column is unlinked from the header list, and the other nodes of its rows
+        // are unlinked from their columns. Secondary headers aren't part of the header list, so
+        // a row whose only node is in a covered secondary column can't be told apart.
+        let available = (start..end).map(I::from_usize).all(|node| {
+            let col = self.nodes.col(node);
+            self.nodes.down(self.nodes.up(node)) == node
+                && self.nodes.right(self.nodes.left(col)) == col
+        });
+        if !available {
+            return false;
+        }
+
+        let node = I::from_usize(start);
+        let col = self.nodes.col(node);
+        self.cover_column(col);
+        self.cover_row(node, &mut NoObserver);
+        self.stack.push((col, node));
+        self.n_selected += 1;
+        self.metrics.max_depth = self.metrics.max_depth.max(self.stack.len());
+        true
+    }
+
+    // Adds the row of the given node to the partial solution
+    #[inline(always)]
+    fn push_row(&mut self, col_hdr: I, col_node: I, observer: &mut impl SearchObserver) {
+        let row = self.nodes.row(col_node).to_usize();
+        self.notify(observer, SearchEvent::SelectRow { row });
+        let timer = self.start_timer();
+        self.cover_row(col_node, observer);
+        if let Some(timer) = timer {
+            self.metrics.cover_time += timer.elapsed();
+        }
+        self.stack.push((col_hdr, col_node));
+
+        self.metrics.nodes += 1;
+        self.metrics.max_depth = self.metrics.max_depth.max(self.stack.len());
+    }
+
+    // Undoes the choices on the stack until a row with an untried alternative is found and
+    // covers that alternative instead. Returns false if there are no alternatives left.
+    #[inline(always)]
+    fn backtrack(&mut self, observer: &mut impl SearchObserver) -> bool {
+        while self.stack.len() > self.n_selected {
+            let Some((prev_col_hdr, prev_col_node)) = self.stack.pop() else {
+                break;
+            };
+            self.metrics.backtracks += 1;
+            let row = self.nodes.row(prev_col_node).to_usize();
+            self.notify(observer, SearchEvent::Backtrack { row });
+            let timer = self.start_timer();
+            self.uncover_row(prev_col_node);
+            let next_col_node = self.nodes.down(prev_col_node);
+            if next_col_node != prev_col_hdr {
+                if let Some(timer) = timer {
+                    self.metrics.uncover_time += timer.elapsed();
                 }
-                self.uncover_column(prev_col_hdr);
+                // Found the next row to cover
+                self.push_row(prev_col_hdr, next_col_node, observer);
+                return true;
+            }
+            self.uncover_column(prev_col_hdr);
+            if let Some(timer) = timer {
+                self.metrics.uncover_time += timer.elapsed();
             }
-            if stack.is_empty() {
-                return None; // No more options to backtrack
+        }
+        false
+    }
+
+    // Starts timing a cover or uncover, if details are collected
+    #[inline(always)]
+    fn start_timer(&self) -> Option<Instant> {
+        self.details.then(Instant::now)
+    }
+
+    // Undoes the choices of the search, so that the next search starts from the selected rows
+    fn rewind(&mut self) {
+        while self.stack.len() > self.n_selected {
+            let Some((col_hdr, col_node)) = self.stack.pop() else {
+                break;
+            };
+            self.uncover_row(col_node);
+            self.uncover_column(col_hdr);
+        }
+        self.state = SearchState::Ready;
+        self.stopped = false;
+        self.ticks = 0;
+    }
+
+    // Undoes all choices on the stack including the selected rows, restoring the full matrix
+    fn restore(&mut self) {
+        self.n_selected = 0;
+        self.rewind();
+    }
+
+    // Rewinds to the selected rows and clears the metrics for a new search
+    fn restart(&mut self) {
+        self.rewind();
+        self.clear_metrics();
+    }
+
+    fn reset(&mut self) {
+        self.restore();
+        self.clear_metrics();
+    }
+
+    fn clear_metrics(&mut self) {
+        self.metrics = SearchMetrics {
+            max_depth: self.stack.len(),
+            ..SearchMetrics::default()
+        };
+        if self.details {
+            // The header nodes follow the root
+            self.metrics.column_selections = vec![0; self.col_counts.0.len() - 1];
+        }
+    }
+}
+
+/// An exact cover problem solved with Knuth's Dancing Links implementation of Algorithm X.
+///
+/// The matrix is given in sparse form: every row lists the indices of the columns in which it
+/// has a one. A solution is a set of rows, such that every column contains exactly one one.
+///
+/// Optionally, the last columns of the matrix can be secondary columns, which have to be covered
+/// at most once instead of exactly once.
+///
+/// The index type `I` has to be able to address every node of the grid, i.e. the number of
+/// columns plus the total number of ones plus one. `u16` is enough for a 9x9 sudoku, bigger
+/// problems need `u32` or wider.
+pub struct ExactCover<I: Index = u16> {
+    grid: NodeGrid<I>,
+    n_rows: usize,
+    n_cols: usize,
+}
+
+impl<I: Index> ExactCover<I> {
+    pub fn new<R: AsRef<[I]>>(rows: &[R], n_cols: usize) -> Result<Self, ExactCoverError> {
+        Self::with_secondary_columns(rows, n_cols, 0)
+    }
+
+    /// Creates a problem with `n_primary` columns which have to be covered exactly once,
+    /// followed by `n_secondary` columns which have to be covered at most once.
+    pub fn with_secondary_columns<R: AsRef<[I]>>(
+        rows: &[R],
+        n_primary: usize,
+        n_secondary: usize,
+    ) -> Result<Self, ExactCoverError> {
+        let n_cols = n_primary + n_secondary;
+        let mut last_seen_in_row = vec![usize::MAX; n_cols];
+        let mut n_nodes = n_cols + 1;
+        for (row_idx, row) in rows.iter().enumerate() {
+            for &col in row.as_ref() {
+                let col = col.to_usize();
+                if col >= n_cols {
+                    return Err(ExactCoverError::ColumnOutOfRange {
+                        row: row_idx,
+                        col,
+                        n_cols,
+                    });
+                }
+                if last_seen_in_row[col] == row_idx {
+                    return Err(ExactCoverError::DuplicateColumn { row: row_idx, col });
+                }
+                last_seen_in_row[col] = row_idx;
             }
+            n_nodes += row.as_ref().len();
         }
+
+        let max_idx = I::max_usize();
+        if n_nodes - 1 > max_idx || rows.len() > max_idx {
+            return Err(ExactCoverError::IndexOverflow(n_nodes));
+        }
+
+        Ok(Self {
+            grid: NodeGrid::from_sparse_matrix(rows, n_primary, n_cols),
+            n_rows: rows.len(),
+            n_cols,
+        })
+    }
+
+    pub fn n_rows(&self) -> usize {
+        self.n_rows
+    }
+
+    pub fn n_cols(&self) -> usize {
+        self.n_cols
+    }
+
+    /// Searches for a set of rows that exactly covers all columns and returns their indices.
+    ///
+    /// The search always starts from the selected rows, or from scratch without any, use
+    /// [`ExactCover::next_solution`] to continue a previous search instead.
+    pub fn search(&mut self) -> Option<Vec<usize>> {
+        self.grid.restart();
+        self.next_solution()
+    }
+
+    /// Continues the search where the last call stopped and returns the next solution.
+    pub fn next_solution(&mut self) -> Option<Vec<usize>> {
+        let mut solution = Vec::new();
+        self.next_solution_into(&mut solution).then_some(solution)
+    }
+
+    /// Like [`ExactCover::next_solution`], but replaces the contents of `solution` instead of
+    /// allocating a new vector. Returns false if there are no more solutions.
+    pub fn next_solution_into(&mut self, solution: &mut Vec<usize>) -> bool {
+        self.grid.next_solution_into(solution, &mut NoObserver)
+    }
+
+    /// Like [`ExactCover::next_solution_into`], but reports every step of the search to the
+    /// observer.
+    pub fn next_solution_observed(
+        &mut self,
+        solution: &mut Vec<usize>,
+        observer: &mut impl SearchObserver,
+    ) -> bool {
+        self.grid.next_solution_into(solution, observer)
+    }
+
+    /// Adds the given row to every following solution, e.g. for the givens of a sudoku.
+    ///
+    /// This abandons the current search. Returns false without selecting the row, if it is
+    /// empty or shares a column with an already selected row. Rows whose only column is a
+    /// secondary one are an exception, they can't be checked against the selected rows.
+    /// The selection is undone by [`ExactCover::reset`].
+    ///
+    /// Panics if the row is out of range.
+    pub fn select_row(&mut self, row: usize) -> bool {
+        self.grid.select_row(row)
+    }
+
+    /// Restores the full matrix including the selected rows, so that the next search starts
+    /// from scratch.
+    pub fn reset(&mut self) {
+        self.grid.reset();
+    }
+
+    /// Counts the solutions including the selected rows, but stops as soon as `limit` solutions
+    /// have been found or one of the limits is exceeded.
+    pub fn count_solutions(&mut self, limit: usize) -> usize {
+        self.grid.count_solutions(limit)
+    }
+
+    /// Sets the limits of the following searches. Once one of them is exceeded, the search
+    /// stops as if there were no more solutions, and [`ExactCover::interruption`] tells why.
+    pub fn set_limits(&mut self, limits: SearchLimits) {
+        self.grid.limits = limits;
+    }
+
+    /// Tells why the current search was stopped early, if it was.
+    pub fn interruption(&self) -> Option<Interruption> {
+        match self.grid.state {
+            SearchState::Interrupted(interruption) => Some(interruption),
+            _ => None,
+        }
+    }
+
+    /// Returns the size of the search tree explored since the last search was started.
+    pub fn metrics(&self) -> SearchMetrics {
+        self.grid.metrics.clone()
+    }
+
+    /// Enables collecting the column selections and cover times of the following searches.
+    ///
+    /// This abandons the current search, but keeps the rows selected by
+    /// [`ExactCover::select_row`].
+    pub fn collect_details(&mut self, enabled: bool) {
+        self.grid.details = enabled;
+        self.grid.restart();
+    }
+
+    /// Returns a lazy iterator over all solutions, starting from the selected rows.
+    pub fn solutions(&mut self) -> Solutions<'_, I> {
+        self.grid.restart();
+        Solutions { exact_cover: self }
+    }
+}
+
+pub struct Solutions<'a, I: Index> {
+    exact_cover: &'a mut ExactCover<I>,
+}
+
+impl<I: Index> Iterator for Solutions<'_, I> {
+    type Item = Vec<usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.exact_cover.next_solution()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The example from Knuth's "Dancing Links" paper, the only solution are the rows 0, 3 and 4.
+    fn knuth_example() -> Vec<Vec<u16>> {
+        vec![
+            vec![2, 4, 5],
+            vec![0, 3, 6],
+            vec![1, 2, 5],
+            vec![0, 3],
+            vec![1, 6],
+            vec![3, 4, 6],
+        ]
+    }
+
+    #[test]
+    fn test_knuth_example() -> Result<(), ExactCoverError> {
+        let mut exact_cover = ExactCover::new(&knuth_example(), 7)?;
+        let mut solution = exact_cover.search().unwrap();
+        solution.sort();
+        assert_eq!(solution, vec![0, 3, 4]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_metrics() -> Result<(), ExactCoverError> {
+        let mut exact_cover = ExactCover::new(&knuth_example(), 7)?;
+        exact_cover.search().unwrap();
+        let metrics = exact_cover.metrics();
+        assert_eq!(metrics.max_depth, 3);
+        assert!(metrics.nodes >= 3);
+        assert_eq!(metrics.nodes - metrics.backtracks, 3);
+        assert!(metrics.column_selections.is_empty());
+        assert_eq!(metrics.cover_time, Duration::ZERO);
+
+        // The first column has the fewest rows and is chosen first
+        exact_cover.collect_details(true);
+        exact_cover.search().unwrap();
+        let details = exact_cover.metrics();
+        assert_eq!(details.nodes, metrics.nodes);
+        assert_eq!(details.column_selections.len(), 7);
+        assert!(details.column_selections[0] >= 1);
+        assert!(details.cover_time > Duration::ZERO);
+
+        let mut total = SearchMetrics::default();
+        total.add(&details);
+        total.add(&details);
+        assert_eq!(total.nodes, 2 * details.nodes);
+        assert_eq!(total.max_depth, 3);
+        assert_eq!(total.column_selections[0], 2 * details.column_selections[0]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_wide_index_type() -> Result<(), ExactCoverError> {
+        let rows: Vec<Vec<u32>> = knuth_example()
+            .into_iter()
+            .map(|row| row.into_iter().map(u32::from).collect())
+            .collect();
+        let mut exact_cover = ExactCover::new(&rows, 7)?;
+        let mut solution = exact_cover.search().unwrap();
+        solution.sort();
+        assert_eq!(solution, vec![0, 3, 4]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_odd_number_of_columns() -> Result<(), ExactCoverError> {
+        let mut exact_cover = ExactCover::<u16>::new(&[vec![0, 2], vec![1], vec![0]], 3)?;
+        let mut solution = exact_cover.search().unwrap();
+        solution.sort();
+        assert_eq!(solution, vec![0, 1]);
+
+        let mut exact_cover = ExactCover::<u16>::new(&[vec![0]], 1)?;
+        assert_eq!(exact_cover.search(), Some(vec![0]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_solution() -> Result<(), ExactCoverError> {
+        let mut exact_cover = ExactCover::<u16>::new(&[vec![0, 1], vec![1, 2]], 3)?;
+        assert_eq!(exact_cover.search(), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_all_solutions() -> Result<(), ExactCoverError> {
+        let rows: Vec<Vec<u16>> = vec![vec![0], vec![1], vec![0, 1], vec![2], vec![1, 2]];
+        let mut exact_cover = ExactCover::new(&rows, 3)?;
+        let mut solutions = exact_cover
+            .solutions()
+            .map(|mut solution| {
+                solution.sort();
+                solution
+            })
+            .collect::<Vec<_>>();
+        solutions.sort();
+        assert_eq!(solutions, vec![vec![0, 1, 3], vec![0, 4], vec![2, 3]]);
+
+        // The search restores the matrix once it is exhausted, so it can be run again
+        assert_eq!(exact_cover.solutions().count(), 3);
+        assert_eq!(exact_cover.next_solution(), None);
+
+        // Starting a new search after stopping early works as well
+        assert!(exact_cover.next_solution().is_none());
+        assert!(exact_cover.search().is_some());
+        assert_eq!(exact_cover.solutions().count(), 3);
+
+        assert_eq!(exact_cover.count_solutions(usize::MAX), 3);
+        assert_eq!(
+            exact_cover.metrics(),
+            SearchMetrics {
+                nodes: 6,
+                backtracks: 6,
+                max_depth: 3,
+                ..SearchMetrics::default()
+            }
+        );
+        assert_eq!(exact_cover.count_solutions(2), 2);
+        assert_eq!(exact_cover.count_solutions(0), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_observer() -> Result<(), ExactCoverError> {
+        let mut exact_cover = ExactCover::new(&knuth_example(), 7)?;
+        let mut events = Vec::new();
+        let mut solution = Vec::new();
+        assert!(exact_cover.next_solution_observed(&mut solution, &mut events));
+
+        // The first column has the fewest rows, and covering its row 3 covers column 3 as well
+        assert_eq!(
+            events[..4],
+            [
+                SearchEvent::ChooseColumn { col: 0, count: 2 },
+                SearchEvent::CoverColumn { col: 0 },
+                SearchEvent::SelectRow { row: 1 },
+                SearchEvent::CoverColumn { col: 3 },
+            ]
+        );
+        assert_eq!(events.last(), Some(&SearchEvent::Solution));
+        let count = |f: fn(&SearchEvent) -> bool| events.iter().filter(|e| f(e)).count() as u64;
+        let metrics = exact_cover.metrics();
+        assert_eq!(
+            count(|e| matches!(e, SearchEvent::SelectRow { .. })),
+            metrics.nodes
+        );
+        assert_eq!(
+            count(|e| matches!(e, SearchEvent::Backtrack { .. })),
+            metrics.backtracks
+        );
+
+        // Stopping at the first row ends the search without reporting anything else
+        let mut exact_cover = ExactCover::new(&knuth_example(), 7)?;
+        let mut events = Vec::new();
+        let mut observer = |event| {
+            events.push(event);
+            match event {
+                SearchEvent::SelectRow { .. } => ControlFlow::Break(()),
+                _ => ControlFlow::Continue(()),
+            }
+        };
+        assert!(!exact_cover.next_solution_observed(&mut solution, &mut observer));
+        assert!(!exact_cover.next_solution_observed(&mut solution, &mut observer));
+        assert_eq!(events.last(), Some(&SearchEvent::SelectRow { row: 1 }));
+        assert_eq!(exact_cover.interruption(), Some(Interruption::Cancelled));
+
+        // A new search starts over
+        exact_cover.reset();
+        let mut solution = exact_cover.next_solution().unwrap();
+        solution.sort();
+        assert_eq!(solution, vec![0, 3, 4]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_limits() -> Result<(), ExactCoverError> {
+        let mut exact_cover = ExactCover::new(&knuth_example(), 7)?;
+        exact_cover.set_limits(SearchLimits {
+            max_nodes: Some(1),
+            ..SearchLimits::default()
+        });
+        assert_eq!(exact_cover.search(), None);
+        assert_eq!(exact_cover.interruption(), Some(Interruption::NodeLimit));
+        assert_eq!(exact_cover.metrics().nodes, 1);
+        assert_eq!(exact_cover.next_solution(), None);
+
+        let cancel = Arc::new(AtomicBool::new(true));
+        exact_cover.set_limits(SearchLimits {
+            cancel: Some(cancel.clone()),
+            ..SearchLimits::default()
+        });
+        assert_eq!(exact_cover.search(), None);
+        assert_eq!(exact_cover.interruption(), Some(Interruption::Cancelled));
+        cancel.store(false, Ordering::Relaxed);
+        assert!(exact_cover.search().is_some());
+        assert_eq!(exact_cover.interruption(), None);
+
+        exact_cover.set_limits(SearchLimits::timeout(Duration::ZERO));
+        assert_eq!(exact_cover.count_solutions(1), 0);
+        assert_eq!(exact_cover.interruption(), Some(Interruption::TimedOut));
+
+        // Without limits the interrupted search is restored like any other
+        exact_cover.set_limits(SearchLimits::default());
+        assert_eq!(exact_cover.count_solutions(usize::MAX), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_select_rows() -> Result<(), ExactCoverError> {
+        let rows: Vec<Vec<u16>> = vec![vec![0], vec![1], vec![0, 1], vec![2], vec![1, 2]];
+        let mut exact_cover = ExactCover::new(&rows, 3)?;
+
+        // Selected rows are part of every solution and survive new searches
+        assert!(exact_cover.select_row(1));
+        assert!(!exact_cover.select_row(4));
+        assert!(!exact_cover.select_row(1));
+        assert_eq!(exact_cover.count_solutions(usize::MAX), 1);
+        let mut solution = Vec::new();
+        assert!(exact_cover.next_solution_into(&mut solution));
+        solution.sort();
+        assert_eq!(solution, vec![0, 1, 3]);
+        assert!(!exact_cover.next_solution_into(&mut solution));
+        assert_eq!(exact_cover.search().map(|s| s.len()), Some(3));
+        assert_eq!(exact_cover.metrics().max_depth, 3);
+        exact_cover.collect_details(true);
+        assert_eq!(exact_cover.count_solutions(usize::MAX), 1);
+        assert_eq!(exact_cover.metrics().column_selections.len(), 3);
+        exact_cover.collect_details(false);
+
+        // Selected rows which form a solution on their own, until the selection is reset
+        exact_cover.reset();
+        assert!(exact_cover.select_row(2));
+        assert!(exact_cover.select_row(3));
+        assert_eq!(exact_cover.search(), Some(vec![2, 3]));
+        assert_eq!(exact_cover.metrics().nodes, 0);
+        assert_eq!(exact_cover.metrics().max_depth, 2);
+        exact_cover.reset();
+        assert_eq!(exact_cover.solutions().count(), 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_secondary_columns() -> Result<(), ExactCoverError> {
+        // Column 2 is secondary, so it may stay uncovered, but rows 0 and 1 can't be combined
+        let rows: Vec<Vec<u16>> = vec![vec![0, 2], vec![1, 2], vec![0], vec![1]];
+        let mut exact_cover = ExactCover::with_secondary_columns(&rows, 2, 1)?;
+        assert_eq!(exact_cover.n_cols(), 3);
+        let mut solutions = exact_cover
+            .solutions()
+            .map(|mut solution| {
+                solution.sort();
+                solution
+            })
+            .collect::<Vec<_>>();
+        solutions.sort();
+        assert_eq!(solutions, vec![vec![0, 3], vec![1, 2], vec![2, 3]]);
+
+        // Without primary columns the empty set is the only solution
+        let mut exact_cover = ExactCover::<u16>::with_secondary_columns(&rows, 0, 3)?;
+        assert_eq!(exact_cover.search(), Some(vec![]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_matrices() {
+        assert_eq!(
+            ExactCover::<u16>::new(&[vec![0, 3]], 3).err(),
+            Some(ExactCoverError::ColumnOutOfRange {
+                row: 0,
+                col: 3,
+                n_cols: 3
+            })
+        );
+        assert_eq!(
+            ExactCover::<u16>::new(&[vec![0], vec![1, 1]], 3).err(),
+            Some(ExactCoverError::DuplicateColumn { row: 1, col: 1 })
+        );
+        // 126 rows of two columns make 255 nodes with the header and the columns, so the
+        // largest index is 254 and fits into a `u8`. One more row needs the index 256.
+        assert!(ExactCover::new(&vec![vec![0u8, 1]; 126], 2).is_ok());
+        let rows = vec![vec![0u8, 1]; 127];
+        assert_eq!(
+            ExactCover::new(&rows, 2).err(),
+            Some(ExactCoverError::IndexOverflow(257))
+        );
     }
 }
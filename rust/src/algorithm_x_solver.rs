@@ -1,91 +1,439 @@
-use crate::{sudoku::Sudoku, sudoku_solver::SudokuSolver};
+use std::{cell::RefCell, fmt, str::FromStr};
 
-use super::algorithm_x::NodeGrid;
+use crate::{
+    error::{Result, SudokuError},
+    regions::Regions,
+    sudoku::{BoardSize, Sudoku, Variant},
+    sudoku_solver::{SolveOutcome, SudokuSolver, TraceEvent},
+};
 
-pub const NR_CANDIDATES: usize = 9 * 9 * 9; // 729
-pub const NR_CONSTRAINTS: usize = 4 * 9 * 9; // 324
+use super::algorithm_x::{
+    ExactCover, Index, Interruption, SearchEvent, SearchLimits, SearchMetrics, SearchObserver,
+};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 struct Candidate {
     row: u8,
     col: u8,
     num: u8,
 }
 
+// The node index type is chosen per board, so that 9x9 sudokus can use compact u16 nodes while
+// bigger boards fall back to u32.
+enum SudokuExactCover {
+    Small(ExactCover<u16>),
+    Large(ExactCover<u32>),
+}
+
+impl SudokuExactCover {
+    fn next_solution(&mut self) -> Option<Vec<usize>> {
+        match self {
+            Self::Small(exact_cover) => exact_cover.next_solution(),
+            Self::Large(exact_cover) => exact_cover.next_solution(),
+        }
+    }
+
+    fn count_solutions(&mut self, limit: usize) -> usize {
+        match self {
+            Self::Small(exact_cover) => exact_cover.count_solutions(limit),
+            Self::Large(exact_cover) => exact_cover.count_solutions(limit),
+        }
+    }
+
+    fn metrics(&self) -> SearchMetrics {
+        match self {
+            Self::Small(exact_cover) => exact_cover.metrics(),
+            Self::Large(exact_cover) => exact_cover.metrics(),
+        }
+    }
+
+    fn collect_details(&mut self, enabled: bool) {
+        match self {
+            Self::Small(exact_cover) => exact_cover.collect_details(enabled),
+            Self::Large(exact_cover) => exact_cover.collect_details(enabled),
+        }
+    }
+
+    fn next_solution_into(&mut self, solution: &mut Vec<usize>) -> bool {
+        match self {
+            Self::Small(exact_cover) => exact_cover.next_solution_into(solution),
+            Self::Large(exact_cover) => exact_cover.next_solution_into(solution),
+        }
+    }
+
+    fn next_solution_observed(
+        &mut self,
+        solution: &mut Vec<usize>,
+        observer: &mut impl SearchObserver,
+    ) -> bool {
+        match self {
+            Self::Small(exact_cover) => exact_cover.next_solution_observed(solution, observer),
+            Self::Large(exact_cover) => exact_cover.next_solution_observed(solution, observer),
+        }
+    }
+
+    fn select_row(&mut self, row: usize) -> bool {
+        match self {
+            Self::Small(exact_cover) => exact_cover.select_row(row),
+            Self::Large(exact_cover) => exact_cover.select_row(row),
+        }
+    }
+
+    fn reset(&mut self) {
+        match self {
+            Self::Small(exact_cover) => exact_cover.reset(),
+            Self::Large(exact_cover) => exact_cover.reset(),
+        }
+    }
+
+    fn set_limits(&mut self, limits: SearchLimits) {
+        match self {
+            Self::Small(exact_cover) => exact_cover.set_limits(limits),
+            Self::Large(exact_cover) => exact_cover.set_limits(limits),
+        }
+    }
+
+    fn interruption(&self) -> Option<Interruption> {
+        match self {
+            Self::Small(exact_cover) => exact_cover.interruption(),
+            Self::Large(exact_cover) => exact_cover.interruption(),
+        }
+    }
+}
+
 pub struct AlgorithmXSudokuSolver {
-    sparse_mat: Vec<[u16; 4]>,
+    size: BoardSize,
+    variants: Vec<Variant>,
+    regions: Option<Regions>,
+    n_primary: usize,
+    n_secondary: usize,
+    // The constraint columns of all candidates, stored back to back
+    sparse_mat: Vec<u32>,
+    // Where the columns of each candidate start in `sparse_mat`, followed by the end of the last
+    row_starts: Vec<usize>,
     candidates: Vec<Candidate>,
 }
 
 impl AlgorithmXSudokuSolver {
     pub fn new() -> Self {
-        let candidates: Vec<_> = (1..10)
-            .flat_map(|row| (1..10).flat_map(move |col| (1..10).map(move |num| (row, col, num))))
+        Self::for_size(BoardSize::CLASSIC)
+    }
+
+    /// Creates a solver for boards of the given size. Boards of other sizes can still be
+    /// solved, but the exact cover matrix has to be set up again for every one of them.
+    pub fn for_size(size: BoardSize) -> Self {
+        Self::with_variants(size, &[])
+    }
+
+    /// Creates a solver for boards of the given size with additional variant rules. Like for
+    /// the size, boards with other variants have to set up their own exact cover matrix.
+    pub fn with_variants(size: BoardSize, variants: &[Variant]) -> Self {
+        Self::build(size, variants, None)
+    }
+
+    /// Creates a solver for boards with the same size, variants and regions as the given one.
+    pub fn for_board(board: &Sudoku) -> Self {
+        Self::build(board.size(), board.variants(), board.regions().cloned())
+    }
+
+    fn build(size: BoardSize, variants: &[Variant], regions: Option<Regions>) -> Self {
+        let side = size.side();
+        let n_cells = size.n_cells();
+        let n_candidates = n_cells * side;
+
+        let mut variants = variants.to_vec();
+        variants.sort_unstable();
+        variants.dedup();
+
+        // The regions take the place of the boxes in jigsaw sudokus
+        let boxes = regions.clone().unwrap_or_else(|| Regions::boxes(size));
+
+        let candidates: Vec<_> = (1..=side as u8)
+            .flat_map(|row| {
+                (1..=side as u8)
+                    .flat_map(move |col| (1..=side as u8).map(move |num| (row, col, num)))
+            })
             .map(|(row, col, num)| Candidate { row, col, num })
             .collect();
 
+        // Variant units have to contain every number exactly once, just like rows or boxes
+        let variant_units: Vec<_> = variants
+            .iter()
+            .flat_map(|variant| variant.units(size))
+            .collect();
+        let mut cell_units = vec![Vec::new(); n_cells];
+        for (unit_idx, unit) in variant_units.iter().enumerate() {
+            for &(row, col) in unit {
+                cell_units[row * side + col].push(unit_idx);
+            }
+        }
+        let n_primary = 4 * n_cells + variant_units.len() * side;
+
+        // Cells which see each other only because of a variant may not contain the same number,
+        // but they don't have to contain it either. These constraints are secondary columns,
+        // one per pair of cells and number.
+        let mut cell_pairs = vec![Vec::new(); n_cells];
+        let mut n_pairs = 0;
+        for variant in &variants {
+            for idx in 0..n_cells {
+                let (row, col) = (idx / side, idx % side);
+                for (other_row, other_col) in variant.neighbours(size, row, col) {
+                    let other_idx = other_row * side + other_col;
+                    let shares_unit = other_row == row
+                        || other_col == col
+                        || boxes.region(other_row, other_col) == boxes.region(row, col);
+                    if other_idx < idx || shares_unit {
+                        continue;
+                    }
+                    cell_pairs[idx].push(n_pairs);
+                    cell_pairs[other_idx].push(n_pairs);
+                    n_pairs += 1;
+                }
+            }
+        }
+
         // Store just the column indices of the constraints for each candidate
-        let mut sparse_mat: Vec<[u16; 4]> = Vec::with_capacity(NR_CANDIDATES);
+        let mut sparse_mat: Vec<u32> = Vec::with_capacity(4 * n_candidates);
+        let mut row_starts = Vec::with_capacity(n_candidates + 1);
 
         for candidate in &candidates {
             let row = (candidate.row - 1) as usize;
             let col = (candidate.col - 1) as usize;
             let num = (candidate.num - 1) as usize;
+            row_starts.push(sparse_mat.len());
 
             // Cell constraint
             // Each cell (row, col) must be filled with exactly one number
-            let cell_cons_col = row * 9 + col;
+            let cell_cons_col = row * side + col;
 
             // Row constraint
             // Each number must appear exactly once in each row
-            let row_cons_col = row * 9 + num + 81;
+            let row_cons_col = row * side + num + n_cells;
 
             // Column constraint
             // Each number must appear exactly once in each column!
-            let col_cons_col = col * 9 + num + 2 * 81;
+            let col_cons_col = col * side + num + 2 * n_cells;
 
             // Box constraint
-            // Each number must appear exactly once in each 3x3 box
-            let box_row = row / 3;
-            let box_col = col / 3;
-            let box_index = box_row * 3 + box_col;
-            let box_cons_col = box_index * 9 + num + 3 * 81;
+            // Each number must appear exactly once in each box
+            let box_index = boxes.region(row, col);
+            let box_cons_col = box_index * side + num + 3 * n_cells;
 
-            sparse_mat.push([
-                cell_cons_col as u16,
-                row_cons_col as u16,
-                col_cons_col as u16,
-                box_cons_col as u16,
-            ]);
+            sparse_mat.extend(
+                [cell_cons_col, row_cons_col, col_cons_col, box_cons_col].map(|col| col as u32),
+            );
+
+            // Variant unit constraints
+            // Each number must appear exactly once in each variant unit
+            let cell_idx = row * side + col;
+            for &unit_idx in &cell_units[cell_idx] {
+                sparse_mat.push((unit_idx * side + num + 4 * n_cells) as u32);
+            }
+
+            // Variant neighbour constraints
+            // Each number may appear at most once in each pair of neighbouring cells
+            for &pair_idx in &cell_pairs[cell_idx] {
+                sparse_mat.push((pair_idx * side + num + n_primary) as u32);
+            }
         }
+        row_starts.push(sparse_mat.len());
 
         Self {
+            size,
+            variants,
+            regions,
+            n_primary,
+            n_secondary: n_pairs * side,
             sparse_mat,
+            row_starts,
             candidates,
         }
     }
 
-    fn calc_row_idcs(board: &Sudoku) -> Vec<usize> {
-        let mut row_idcs = Vec::with_capacity(NR_CANDIDATES);
+    pub fn size(&self) -> BoardSize {
+        self.size
+    }
+
+    pub fn variants(&self) -> &[Variant] {
+        &self.variants
+    }
+
+    /// Returns a lazy iterator over all solutions of the given board.
+    ///
+    /// Every solution continues the search of the previous one, instead of starting over.
+    pub fn solutions(&self, board: &Sudoku) -> impl Iterator<Item = Sudoku> {
+        let (mut exact_cover, candidates) = self.exact_cover(board);
+
+        let board = board.clone();
+        std::iter::from_fn(move || {
+            let solution = exact_cover.next_solution()?;
+            Some(fill(board.clone(), &candidates, &solution))
+        })
+    }
+
+    /// Solves the board like [`SudokuSolver::solve`], and reports every step of the search to
+    /// the observer. The search ends as [`SolveOutcome::Cancelled`] if the observer stops it.
+    pub fn trace(
+        &self,
+        board: Sudoku,
+        observer: &mut impl SearchObserver<TraceEvent>,
+    ) -> SolveOutcome {
+        if !self.fits(&board) {
+            return Self::for_board(&board).trace(board, observer);
+        }
+        let (mut exact_cover, candidates) = self.exact_cover(&board);
+
+        let mut matrix_observer = |event: SearchEvent| {
+            let place = |row: usize| {
+                let candidate = candidates[row];
+                (
+                    candidate.row as usize,
+                    candidate.col as usize,
+                    candidate.num,
+                )
+            };
+            observer.on_event(match event {
+                SearchEvent::ChooseColumn { col, count } => TraceEvent::Choose {
+                    column: self.column_name(col),
+                    count,
+                },
+                SearchEvent::CoverColumn { col } => TraceEvent::Cover {
+                    column: self.column_name(col),
+                },
+                SearchEvent::SelectRow { row } => {
+                    let (row, col, digit) = place(row);
+                    TraceEvent::Place { row, col, digit }
+                }
+                SearchEvent::Backtrack { row } => {
+                    let (row, col, digit) = place(row);
+                    TraceEvent::Backtrack { row, col, digit }
+                }
+                SearchEvent::Solution => TraceEvent::Solution,
+            })
+        };
+        let mut solution = Vec::new();
+        if exact_cover.next_solution_observed(&mut solution, &mut matrix_observer) {
+            return SolveOutcome::Solved(fill(board, &candidates, &solution));
+        }
+        exact_cover
+            .interruption()
+            .map_or(SolveOutcome::NoSolution, SolveOutcome::from)
+    }
+
+    /// Like [`SudokuSolver::count_solutions`], but also returns the size of the
+    /// search tree that had to be explored.
+    pub fn count_solutions_with_metrics(
+        &self,
+        board: &Sudoku,
+        limit: usize,
+    ) -> (usize, SearchMetrics) {
+        let mut exact_cover = self.exact_cover(board).0;
+        let count = exact_cover.count_solutions(limit);
+        (count, exact_cover.metrics())
+    }
+
+    // Builds the exact cover problem for the given board and returns it together with the
+    // candidates its rows correspond to.
+    fn exact_cover(&self, board: &Sudoku) -> (SudokuExactCover, Vec<Candidate>) {
+        if !self.fits(board) {
+            return Self::for_board(board).exact_cover(board);
+        }
+
+        // Prepare the list of row indices to select from the exact cover matrix
+        let row_idcs = self.calc_row_idcs(board);
+        let exact_cover = self.sub_matrix(&row_idcs);
+
+        let candidates = row_idcs
+            .into_iter()
+            .map(|idx| self.candidates[idx])
+            .collect::<Vec<_>>();
+
+        (exact_cover, candidates)
+    }
+
+    // Creates an exact cover problem containing only the given rows of the full matrix, with the
+    // narrowest node index type that fits
+    fn sub_matrix(&self, row_idcs: &[usize]) -> SudokuExactCover {
+        let n_ones: usize = row_idcs.iter().map(|&idx| self.row(idx).len()).sum();
+        let n_nodes = self.n_primary + self.n_secondary + 1 + n_ones;
+        if n_nodes <= u16::MAX as usize {
+            SudokuExactCover::Small(self.sub_matrix_exact_cover(row_idcs))
+        } else {
+            SudokuExactCover::Large(self.sub_matrix_exact_cover(row_idcs))
+        }
+    }
+
+    // The index of the candidate for the given cell and number, starting at 0
+    fn candidate_idx(&self, row: usize, col: usize, num: usize) -> usize {
+        let side = self.size.side();
+        side * side * row + side * col + num
+    }
+
+    // Checks whether the exact cover matrix was set up for boards like the given one
+    fn fits(&self, board: &Sudoku) -> bool {
+        board.size() == self.size
+            && board.variants() == self.variants
+            && board.regions() == self.regions.as_ref()
+    }
+
+    // The constraint columns of the candidate with the given index
+    fn row(&self, idx: usize) -> &[u32] {
+        &self.sparse_mat[self.row_starts[idx]..self.row_starts[idx + 1]]
+    }
+
+    // Creates an exact cover problem containing only the given rows of the full matrix
+    fn sub_matrix_exact_cover<I: Index>(&self, row_idcs: &[usize]) -> ExactCover<I> {
+        let columns = row_idcs
+            .iter()
+            .flat_map(|&idx| self.row(idx))
+            .map(|&col| I::from_usize(col as usize))
+            .collect::<Vec<_>>();
+        // Split the converted columns back into rows
+        let mut start = 0;
+        let sparse_sub_mat = row_idcs
+            .iter()
+            .map(|&idx| {
+                let end = start + self.row(idx).len();
+                let row = &columns[start..end];
+                start = end;
+                row
+            })
+            .collect::<Vec<_>>();
+        ExactCover::with_secondary_columns(&sparse_sub_mat, self.n_primary, self.n_secondary)
+            .expect("The sudoku constraint matrix is always valid")
+    }
+
+    fn calc_row_idcs(&self, board: &Sudoku) -> Vec<usize> {
+        let side = self.size.side();
+        let mut row_idcs = Vec::with_capacity(self.candidates.len());
         for (i, &elem) in board.iter().enumerate() {
-            let row = i / 9;
-            let col = i % 9;
+            let row = i / side;
+            let col = i % side;
 
             if elem == 0 {
-                // If the cell is empty, we need to consider all possible numbers (1-9)
-                let idx = 81 * row + 9 * col;
-                row_idcs.extend(idx..idx + 9);
+                // If the cell is empty, we need to consider all possible numbers
+                let idx = self.candidate_idx(row, col, 0);
+                row_idcs.extend(idx..idx + side);
             } else {
                 // If the cell is filled, we only consider that specific number
-                let num = (elem - 1) as usize;
-                let idx = 81 * row + 9 * col + num;
-                row_idcs.push(idx);
+                row_idcs.push(self.candidate_idx(row, col, (elem - 1) as usize));
             }
         }
         row_idcs
     }
 }
 
+// Places the candidates of a solution into the board
+fn fill(mut board: Sudoku, candidates: &[Candidate], solution: &[usize]) -> Sudoku {
+    for &idx in solution {
+        let candidate = candidates[idx];
+        let row = (candidate.row - 1) as usize;
+        let col = (candidate.col - 1) as usize;
+        board.set(row, col, candidate.num);
+    }
+    board
+}
+
 impl Default for AlgorithmXSudokuSolver {
     fn default() -> Self {
         Self::new()
@@ -93,32 +441,653 @@ impl Default for AlgorithmXSudokuSolver {
 }
 
 impl SudokuSolver for AlgorithmXSudokuSolver {
-    fn solve(&self, mut board: Sudoku) -> Option<Sudoku> {
-        // Prepare the list of row indices to select from the exact cover matrix
-        let row_idcs = Self::calc_row_idcs(&board);
+    fn solve(&self, board: Sudoku) -> Option<Sudoku> {
+        self.solutions(&board).next()
+    }
 
-        // Create a sub-matrix containing only the relevant rows
-        // let sub_matrix = self.matrix.select(Axis(0), &row_idcs);
-        let sparse_sub_mat = row_idcs
+    fn solve_with_limits(&self, board: Sudoku, limits: &SearchLimits) -> SolveOutcome {
+        let (mut exact_cover, candidates) = self.exact_cover(&board);
+        exact_cover.set_limits(limits.clone());
+        match exact_cover.next_solution() {
+            Some(solution) => SolveOutcome::Solved(fill(board, &candidates, &solution)),
+            None => exact_cover
+                .interruption()
+                .map_or(SolveOutcome::NoSolution, SolveOutcome::from),
+        }
+    }
+
+    fn count_solutions(&self, board: &Sudoku, limit: usize) -> usize {
+        self.count_solutions_with_metrics(board, limit).0
+    }
+
+    fn solve_with_metrics(&self, board: Sudoku) -> (Option<Sudoku>, SearchMetrics) {
+        let (mut exact_cover, candidates) = self.exact_cover(&board);
+        exact_cover.collect_details(true);
+        let solution = exact_cover.next_solution();
+        let metrics = exact_cover.metrics();
+        (
+            solution.map(|solution| fill(board, &candidates, &solution)),
+            metrics,
+        )
+    }
+
+    fn solve_and_count(
+        &self,
+        board: Sudoku,
+        limit: usize,
+        metrics: Option<&mut SearchMetrics>,
+    ) -> (Option<Sudoku>, usize) {
+        let (mut exact_cover, candidates) = self.exact_cover(&board);
+        exact_cover.collect_details(metrics.is_some());
+        let (mut first, mut solution) = (None, Vec::new());
+        let mut count = 0;
+        while count < limit && exact_cover.next_solution_into(&mut solution) {
+            if count == 0 {
+                first = Some(std::mem::take(&mut solution));
+            }
+            count += 1;
+        }
+        if let Some(metrics) = metrics {
+            *metrics = exact_cover.metrics();
+        }
+        (first.map(|first| fill(board, &candidates, &first)), count)
+    }
+
+    fn column_name(&self, col: usize) -> String {
+        let side = self.size.side();
+        let n_cells = self.size.n_cells();
+        let digit = BoardSize::digit_to_char((col % side) as u8 + 1);
+        let unit = (col % n_cells) / side + 1;
+        match col / n_cells {
+            0 => format!("r{}c{}", unit, col % side + 1),
+            1 => format!("{} in row {}", digit, unit),
+            2 => format!("{} in column {}", digit, unit),
+            3 => format!("{} in box {}", digit, unit),
+            _ if col < self.n_primary => {
+                let unit = (col - 4 * n_cells) / side + 1;
+                format!("{} in variant unit {}", digit, unit)
+            }
+            _ => format!("{} in a pair of variant neighbours", digit),
+        }
+    }
+}
+
+/// How the exact cover matrix of a board is set up for the search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatrixStrategy {
+    /// Builds a matrix of only the candidates of every board, as [`AlgorithmXSudokuSolver`] does
+    SubMatrix,
+    /// Covers the givens in a matrix of all candidates which is built once, as
+    /// [`AlgorithmXWorkspaceSolver`] does
+    FullMatrix,
+}
+
+impl MatrixStrategy {
+    pub const ALL: [MatrixStrategy; 2] = [MatrixStrategy::SubMatrix, MatrixStrategy::FullMatrix];
+}
+
+impl fmt::Display for MatrixStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MatrixStrategy::SubMatrix => write!(f, "sub-matrix"),
+            MatrixStrategy::FullMatrix => write!(f, "full-matrix"),
+        }
+    }
+}
+
+impl FromStr for MatrixStrategy {
+    type Err = SudokuError;
+
+    /// Parses a strategy by its name, `sub-matrix` or `full-matrix`.
+    fn from_str(s: &str) -> Result<MatrixStrategy> {
+        SudokuError::parse_name(s, "matrix strategy", &MatrixStrategy::ALL)
+    }
+}
+
+/// An [`AlgorithmXSudokuSolver`] which keeps the exact cover matrix of all candidates around,
+/// for solving many boards one after another.
+///
+/// This is the [`MatrixStrategy::FullMatrix`]: instead of setting up a new matrix for every
+/// board, the rows of the givens are covered in the full matrix and uncovered again once the
+/// search is done. After the first search, boards which fit the solver are solved and counted
+/// without allocating, others fall back to the plain solver. The matrix is borrowed by every
+/// solve, so the solver can be moved to another thread, but not shared between threads.
+pub struct AlgorithmXWorkspaceSolver {
+    solver: AlgorithmXSudokuSolver,
+    workspace: RefCell<Workspace>,
+}
+
+struct Workspace {
+    exact_cover: SudokuExactCover,
+    solution: Vec<usize>,
+}
+
+impl AlgorithmXWorkspaceSolver {
+    pub fn new(solver: AlgorithmXSudokuSolver) -> Self {
+        let all_rows: Vec<usize> = (0..solver.candidates.len()).collect();
+        let workspace = Workspace {
+            exact_cover: solver.sub_matrix(&all_rows),
+            solution: Vec::with_capacity(solver.size.n_cells()),
+        };
+        Self {
+            solver,
+            workspace: RefCell::new(workspace),
+        }
+    }
+
+    pub fn solver(&self) -> &AlgorithmXSudokuSolver {
+        &self.solver
+    }
+
+    // Covers the rows of the givens of the board, searches the rest of the matrix with `search`
+    // and restores the full matrix afterwards. Returns None if two givens contradict each other.
+    fn with_givens<T>(
+        &self,
+        board: &Sudoku,
+        search: impl FnOnce(&mut Workspace) -> T,
+    ) -> Option<T> {
+        let side = self.solver.size.side();
+        let mut workspace = self.workspace.borrow_mut();
+        let selected = board
             .iter()
-            .map(|&idx| self.sparse_mat[idx])
-            .collect::<Vec<_>>();
-        let solution = NodeGrid::from_sparse_matrix(&sparse_sub_mat, NR_CONSTRAINTS).search()?;
+            .enumerate()
+            .filter(|&(_, &elem)| elem != 0)
+            .all(|(i, &elem)| {
+                let idx = self
+                    .solver
+                    .candidate_idx(i / side, i % side, (elem - 1) as usize);
+                workspace.exact_cover.select_row(idx)
+            });
+        let result = selected.then(|| search(&mut workspace));
+        workspace.exact_cover.reset();
+        result
+    }
+}
 
-        let candidates = row_idcs
-            .into_iter()
-            .map(|idx| &self.candidates[idx])
+impl From<AlgorithmXSudokuSolver> for AlgorithmXWorkspaceSolver {
+    fn from(solver: AlgorithmXSudokuSolver) -> Self {
+        Self::new(solver)
+    }
+}
+
+impl Default for AlgorithmXWorkspaceSolver {
+    fn default() -> Self {
+        Self::new(AlgorithmXSudokuSolver::new())
+    }
+}
+
+impl SudokuSolver for AlgorithmXWorkspaceSolver {
+    fn solve(&self, board: Sudoku) -> Option<Sudoku> {
+        self.solve_with_limits(board, &SearchLimits::default())
+            .solution()
+    }
+
+    fn solve_with_limits(&self, board: Sudoku, limits: &SearchLimits) -> SolveOutcome {
+        if !self.solver.fits(&board) {
+            return self.solver.solve_with_limits(board, limits);
+        }
+        let outcome = self.with_givens(&board, |workspace| {
+            let Workspace {
+                exact_cover,
+                solution,
+            } = workspace;
+            // The limits only apply to this search, not to the following ones
+            let unlimited = limits.is_unlimited();
+            if !unlimited {
+                exact_cover.set_limits(limits.clone());
+            }
+            let found = exact_cover.next_solution_into(solution);
+            let interruption = exact_cover.interruption();
+            if !unlimited {
+                exact_cover.set_limits(SearchLimits::default());
+            }
+            (found, interruption)
+        });
+        match outcome {
+            Some((true, _)) => {
+                // The solution outlives the search, which already restored the matrix
+                let workspace = self.workspace.borrow();
+                SolveOutcome::Solved(fill(board, &self.solver.candidates, &workspace.solution))
+            }
+            Some((false, Some(interruption))) => interruption.into(),
+            Some((false, None)) | None => SolveOutcome::NoSolution,
+        }
+    }
+
+    fn count_solutions(&self, board: &Sudoku, limit: usize) -> usize {
+        if !self.solver.fits(board) {
+            return self.solver.count_solutions(board, limit);
+        }
+        self.with_givens(board, |workspace| {
+            workspace.exact_cover.count_solutions(limit)
+        })
+        .unwrap_or(0)
+    }
+
+    // The givens are selected before the search, so unlike with a sub-matrix they don't count as
+    // nodes of the search tree
+    fn solve_with_metrics(&self, board: Sudoku) -> (Option<Sudoku>, SearchMetrics) {
+        let mut metrics = SearchMetrics::default();
+        let (solution, _) = self.solve_and_count(board, 1, Some(&mut metrics));
+        (solution, metrics)
+    }
+
+    fn solve_and_count(
+        &self,
+        board: Sudoku,
+        limit: usize,
+        mut metrics: Option<&mut SearchMetrics>,
+    ) -> (Option<Sudoku>, usize) {
+        if !self.solver.fits(&board) {
+            return self.solver.solve_and_count(board, limit, metrics);
+        }
+        let found = self.with_givens(&board, |workspace| {
+            let Workspace {
+                exact_cover,
+                solution,
+            } = workspace;
+            if metrics.is_some() {
+                exact_cover.collect_details(true);
+            }
+            let mut first = None;
+            let mut count = 0;
+            while count < limit && exact_cover.next_solution_into(solution) {
+                if count == 0 {
+                    first = Some(fill(board.clone(), &self.solver.candidates, solution));
+                }
+                count += 1;
+            }
+            if let Some(metrics) = metrics.as_deref_mut() {
+                *metrics = exact_cover.metrics();
+                exact_cover.collect_details(false);
+            }
+            (first, count)
+        });
+        found.unwrap_or_else(|| {
+            // Contradicting givens are rejected without a search
+            if let Some(metrics) = metrics {
+                *metrics = SearchMetrics::default();
+            }
+            (None, 0)
+        })
+    }
+
+    fn column_name(&self, col: usize) -> String {
+        self.solver.column_name(col)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sudoku_solver::Uniqueness;
+    use std::{
+        ops::ControlFlow,
+        sync::{atomic::AtomicBool, Arc},
+        time::Duration,
+    };
+
+    #[test]
+    fn test_unique_solution() {
+        let solver = AlgorithmXSudokuSolver::new();
+        let sudoku = Sudoku::try_from_str(
+            "4.....8.5.3..........7......2.....6.....8.4......1.......6.3.7.5..2.....1.4......",
+        )
+        .unwrap();
+
+        let solutions = solver.solutions(&sudoku).collect::<Vec<_>>();
+        assert_eq!(solutions.len(), 1);
+        assert!(solutions[0].is_solved());
+        assert_eq!(
+            solutions[0].serialize(),
+            solver.solve(sudoku).unwrap().serialize()
+        );
+    }
+
+    #[test]
+    fn test_multiple_solutions() {
+        let solver = AlgorithmXSudokuSolver::new();
+        let solutions = solver
+            .solutions(&Sudoku::new())
+            .take(10)
             .collect::<Vec<_>>();
+        assert_eq!(solutions.len(), 10);
+        assert!(solutions.iter().all(Sudoku::is_solved));
 
-        for idx in solution {
-            let candidate = candidates[idx as usize];
-            let row = (candidate.row - 1) as usize;
-            let col = (candidate.col - 1) as usize;
-            let num = candidate.num;
+        let mut serialized = solutions.iter().map(Sudoku::serialize).collect::<Vec<_>>();
+        serialized.sort();
+        serialized.dedup();
+        assert_eq!(serialized.len(), 10);
+    }
+
+    #[test]
+    fn test_count_solutions() {
+        let solver = AlgorithmXSudokuSolver::new();
+        let unique = Sudoku::try_from_str(
+            "4.....8.5.3..........7......2.....6.....8.4......1.......6.3.7.5..2.....1.4......",
+        )
+        .unwrap();
+        assert_eq!(solver.count_solutions(&unique, 10), 1);
+        assert_eq!(solver.is_unique(&unique), Uniqueness::Unique);
+
+        // Removing the first clue allows for more solutions
+        let mut ambiguous = unique.clone();
+        ambiguous.set(0, 0, 0);
+        assert_eq!(solver.count_solutions(&ambiguous, 5), 5);
+        assert_eq!(solver.count_solutions(&ambiguous, 0), 0);
+        assert_eq!(solver.is_unique(&ambiguous), Uniqueness::Multiple);
+
+        // Two 4s in the first row can't be solved
+        let mut invalid = unique;
+        invalid.set(0, 1, 4);
+        assert_eq!(solver.count_solutions(&invalid, 2), 0);
+        assert_eq!(solver.is_unique(&invalid), Uniqueness::None);
+    }
+
+    #[test]
+    fn test_solve_and_count() {
+        let unique = Sudoku::try_from_str(
+            "4.....8.5.3..........7......2.....6.....8.4......1.......6.3.7.5..2.....1.4......",
+        )
+        .unwrap();
+        let expected = AlgorithmXSudokuSolver::new().solve(unique.clone()).unwrap();
+        let mut ambiguous = unique.clone();
+        ambiguous.set(0, 0, 0);
+        let mut invalid = unique.clone();
+        invalid.set(0, 1, 4);
+
+        let solvers: [Box<dyn SudokuSolver>; 2] = [
+            Box::new(AlgorithmXSudokuSolver::new()),
+            Box::new(AlgorithmXWorkspaceSolver::default()),
+        ];
+        for solver in &solvers {
+            let (solution, count) = solver.solve_and_count(unique.clone(), 2, None);
+            assert_eq!(solution.unwrap().serialize(), expected.serialize());
+            assert_eq!(count, 1);
+
+            let mut metrics = SearchMetrics::default();
+            let (solution, count) =
+                solver.solve_and_count(ambiguous.clone(), 2, Some(&mut metrics));
+            assert!(solution.unwrap().is_solved());
+            assert_eq!(count, 2);
+            assert!(metrics.nodes > 0);
+
+            let (solution, count) = solver.solve_and_count(ambiguous.clone(), 0, None);
+            assert!(solution.is_none());
+            assert_eq!(count, 0);
+            let (solution, count) = solver.solve_and_count(invalid.clone(), 2, None);
+            assert!(solution.is_none());
+            assert_eq!(count, 0);
+        }
+    }
+
+    #[test]
+    fn test_solve_with_metrics() {
+        let solver = AlgorithmXSudokuSolver::new();
+        let sudoku = Sudoku::try_from_str(
+            "4.....8.5.3..........7......2.....6.....8.4......1.......6.3.7.5..2.....1.4......",
+        )
+        .unwrap();
+        let (solution, metrics) = solver.solve_with_metrics(sudoku.clone());
+        assert_eq!(
+            solution.unwrap().serialize(),
+            solver.solve(sudoku.clone()).unwrap().serialize()
+        );
+        // Every cell needs a row in the solution, the givens included
+        assert_eq!(metrics.max_depth, 81);
+        assert_eq!(metrics.nodes - metrics.backtracks, 81);
+        assert_eq!(metrics.column_selections.len(), 4 * 81);
+        assert!(metrics.column_selections.iter().sum::<u64>() >= 81);
+
+        // The full matrix selects the givens before the search, only the rest are nodes
+        let workspace_solver = AlgorithmXWorkspaceSolver::default();
+        let n_givens = sudoku.iter().filter(|&&elem| elem != 0).count();
+        for _ in 0..2 {
+            let (solution, metrics) = workspace_solver.solve_with_metrics(sudoku.clone());
+            assert!(solution.unwrap().is_solved());
+            assert_eq!(metrics.max_depth, 81);
+            assert_eq!(metrics.nodes - metrics.backtracks, (81 - n_givens) as u64);
+            assert_eq!(metrics.column_selections.len(), 4 * 81);
+        }
+        let mut contradicting = sudoku.clone();
+        contradicting.set(0, 1, 4);
+        let mut metrics = SearchMetrics::default();
+        let (solution, count) =
+            workspace_solver.solve_and_count(contradicting, 2, Some(&mut metrics));
+        assert!(solution.is_none());
+        assert_eq!(count, 0);
+        assert_eq!(metrics.nodes, 0);
 
-            board.set(row, col, num);
+        assert_eq!(solver.column_name(10), "r2c2");
+        assert_eq!(solver.column_name(81 + 9 + 4), "5 in row 2");
+        assert_eq!(solver.column_name(2 * 81), "1 in column 1");
+        assert_eq!(solver.column_name(4 * 81 - 1), "9 in box 9");
+    }
+
+    #[test]
+    fn test_variants() {
+        for variant in [Variant::Diagonal, Variant::Windoku, Variant::AntiKing] {
+            let solver = AlgorithmXSudokuSolver::with_variants(BoardSize::CLASSIC, &[variant]);
+            let mut sudoku = Sudoku::new();
+            sudoku.set_variants(&[variant]);
+            let solution = solver.solve(sudoku).unwrap();
+            assert!(solution.is_solved(), "{}\n{}", variant, solution);
+        }
+
+        // A classic solver sets up the matrix for the variants of the board itself
+        let solver = AlgorithmXSudokuSolver::new();
+        let mut sudoku = Sudoku::try_from_str(
+            "1.3.5.7.9.8.7.9.4.9.7.8.5.6.7.9.5.6.8.9.6.4.5.6.8.4.9.6.8.4......................",
+        )
+        .unwrap();
+        assert_eq!(solver.count_solutions(&sudoku, 10), 10);
+        sudoku.set_variants(&[Variant::AntiKnight]);
+        assert_eq!(solver.count_solutions(&sudoku, 10), 8);
+        assert!(solver
+            .solutions(&sudoku)
+            .all(|solution| solution.is_solved()));
+
+        let mut sudoku = Sudoku::try_from_str(
+            "1.3.5.7.9.7.8.9.1.8.4.2.3.5.5.3.4.9.4.6.8.5.7.8.5.2.4.5.2.1......................",
+        )
+        .unwrap();
+        sudoku.set_variants(&[Variant::Diagonal, Variant::AntiKing]);
+        assert_eq!(solver.is_unique(&sudoku), Uniqueness::Unique);
+        assert_eq!(
+            solver.solve(sudoku).unwrap().serialize(),
+            "123456789675839214894127365257364891436981527981572643542613978369748152718295436"
+        );
+
+        // The classic solution of this puzzle has two 4s on the main diagonal
+        let mut sudoku = Sudoku::try_from_str(
+            "4.....8.5.3..........7......2.....6.....8.4......1.......6.3.7.5..2.....1.4......",
+        )
+        .unwrap();
+        sudoku.set_variants(&[Variant::Diagonal]);
+        assert_eq!(solver.is_unique(&sudoku), Uniqueness::None);
+    }
+
+    #[test]
+    fn test_jigsaw() {
+        let sudoku =
+            Sudoku::try_from_jigsaw_str(include_str!("../resources/jigsaw_sudoku.txt")).unwrap();
+        let solver = AlgorithmXSudokuSolver::for_board(&sudoku);
+        assert_eq!(solver.is_unique(&sudoku), Uniqueness::Unique);
+        let solution = solver.solve(sudoku.clone()).unwrap();
+        assert!(solution.is_solved());
+        assert_eq!(solution.regions(), sudoku.regions());
+
+        // The classic solver sets up the regions of the board itself
+        let solution = AlgorithmXSudokuSolver::new().solve(sudoku).unwrap();
+        assert!(solution.is_solved());
+    }
+
+    #[test]
+    fn test_board_sizes() {
+        let solver = AlgorithmXSudokuSolver::new();
+        for (box_rows, box_cols) in [(2, 2), (2, 3), (3, 4), (4, 4), (5, 5)] {
+            let size = BoardSize::new(box_rows, box_cols).unwrap();
+            let solution = solver.solve(Sudoku::with_size(size)).unwrap();
+            assert_eq!(solution.size(), size);
+            assert!(solution.is_solved(), "{}", solution);
+        }
+
+        let sudoku = Sudoku::try_from_str("1.3..4.2.3..2.4.").unwrap();
+        let solver = AlgorithmXSudokuSolver::for_size(sudoku.size());
+        assert_eq!(solver.is_unique(&sudoku), Uniqueness::Unique);
+        assert_eq!(
+            solver.solve(sudoku).unwrap().serialize(),
+            "1234341243212143"
+        );
+    }
+
+    #[test]
+    fn test_workspace_solver() {
+        let solver = AlgorithmXSudokuSolver::new();
+        let workspace_solver = AlgorithmXWorkspaceSolver::default();
+        let sudokus = [
+            "4.....8.5.3..........7......2.....6.....8.4......1.......6.3.7.5..2.....1.4......",
+            "52...6.........7.13...........4..8..6......5...........418.........3..2...87.....",
+            "6.....8.3.4.7.................5.4.7.3..2.....1.6.......2.....5.....8.6......1....",
+        ];
+        // Every board starts from the full matrix again, whatever the previous one selected
+        for line in sudokus.iter().chain(&sudokus) {
+            let sudoku = Sudoku::try_from_str(line).unwrap();
+            assert_eq!(
+                workspace_solver.solve(sudoku.clone()).unwrap().serialize(),
+                solver.solve(sudoku.clone()).unwrap().serialize()
+            );
+            assert_eq!(workspace_solver.is_unique(&sudoku), Uniqueness::Unique);
+        }
+
+        // Contradicting givens, an empty board and a board which doesn't fit the matrix
+        let mut contradicting = Sudoku::try_from_str(sudokus[0]).unwrap();
+        contradicting.set(0, 1, 4);
+        assert!(workspace_solver.solve(contradicting.clone()).is_none());
+        assert_eq!(workspace_solver.count_solutions(&contradicting, 2), 0);
+        assert_eq!(
+            workspace_solver.is_unique(&Sudoku::new()),
+            Uniqueness::Multiple
+        );
+        let small = Sudoku::try_from_str("1.3..4.2.3..2.4.").unwrap();
+        assert_eq!(
+            workspace_solver.solve(small).unwrap().serialize(),
+            "1234341243212143"
+        );
+
+        let solved = workspace_solver
+            .solve(Sudoku::try_from_str(sudokus[1]).unwrap())
+            .unwrap();
+        assert!(solved.is_solved());
+        assert_eq!(
+            workspace_solver.solve(solved.clone()).unwrap().serialize(),
+            solved.serialize()
+        );
+    }
+
+    #[test]
+    fn test_solve_with_limits() {
+        let sudoku = Sudoku::try_from_str(
+            "4.....8.5.3..........7......2.....6.....8.4......1.......6.3.7.5..2.....1.4......",
+        )
+        .unwrap();
+        let solution = AlgorithmXSudokuSolver::new().solve(sudoku.clone()).unwrap();
+        let cancel = Arc::new(AtomicBool::new(true));
+        let solvers: [Box<dyn SudokuSolver>; 2] = [
+            Box::new(AlgorithmXSudokuSolver::new()),
+            Box::new(AlgorithmXWorkspaceSolver::default()),
+        ];
+
+        for solver in solvers {
+            let node_limit = SearchLimits {
+                max_nodes: Some(10),
+                ..SearchLimits::default()
+            };
+            let cancelled = SearchLimits {
+                cancel: Some(cancel.clone()),
+                ..SearchLimits::default()
+            };
+            assert!(matches!(
+                solver.solve_with_limits(sudoku.clone(), &node_limit),
+                SolveOutcome::NodeLimit
+            ));
+            assert!(matches!(
+                solver.solve_with_limits(sudoku.clone(), &cancelled),
+                SolveOutcome::Cancelled
+            ));
+            assert!(matches!(
+                solver.solve_with_limits(sudoku.clone(), &SearchLimits::timeout(Duration::ZERO)),
+                SolveOutcome::TimedOut
+            ));
+
+            // Interrupted searches don't affect the following ones
+            let outcome =
+                solver.solve_with_limits(sudoku.clone(), &SearchLimits::timeout(Duration::MAX));
+            assert_eq!(
+                outcome.solution().unwrap().serialize(),
+                solution.serialize()
+            );
+            assert_eq!(
+                solver.solve(sudoku.clone()).unwrap().serialize(),
+                solution.serialize()
+            );
         }
+    }
 
-        Some(board)
+    #[test]
+    fn test_trace() {
+        let solver = AlgorithmXSudokuSolver::new();
+        let sudoku = Sudoku::try_from_str(
+            "4.....8.5.3..........7......2.....6.....8.4......1.......6.3.7.5..2.....1.4......",
+        )
+        .unwrap();
+        let mut events = Vec::new();
+        let solution = solver.trace(sudoku.clone(), &mut events).solution();
+        assert_eq!(
+            solution.unwrap().serialize(),
+            solver.solve(sudoku.clone()).unwrap().serialize()
+        );
+
+        // The givens are the first cells with a single option, every cell ends up with one digit
+        assert_eq!(
+            events[..3],
+            [
+                TraceEvent::Choose {
+                    column: "r1c1".to_string(),
+                    count: 1
+                },
+                TraceEvent::Cover {
+                    column: "r1c1".to_string()
+                },
+                TraceEvent::Place {
+                    row: 1,
+                    col: 1,
+                    digit: 4
+                },
+            ]
+        );
+        let places = events
+            .iter()
+            .filter(|event| matches!(event, TraceEvent::Place { .. }))
+            .count();
+        let backtracks = events
+            .iter()
+            .filter(|event| matches!(event, TraceEvent::Backtrack { .. }))
+            .count();
+        assert_eq!(places - backtracks, 81);
+        assert_eq!(events.last(), Some(&TraceEvent::Solution));
+
+        // An observer which can't take any more events stops the search
+        let mut n_events = 0;
+        let mut observer = |_| {
+            n_events += 1;
+            match n_events {
+                10 => ControlFlow::Break(()),
+                _ => ControlFlow::Continue(()),
+            }
+        };
+        let outcome = solver.trace(sudoku, &mut observer);
+        assert!(matches!(outcome, SolveOutcome::Cancelled));
+        assert_eq!(n_events, 10);
     }
 }
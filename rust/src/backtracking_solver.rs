@@ -1,34 +1,739 @@
-use super::sudoku::Sudoku;
-use super::sudoku_solver::SudokuSolver;
+use std::{sync::Arc, time::Instant};
 
-pub struct BacktrackingSolver {}
+use crate::{
+    algorithm_x::{Interruption, SearchLimits, SearchMetrics, SearchObserver},
+    regions::Regions,
+    sudoku::{BoardSize, Sudoku, Variant},
+    sudoku_solver::{SolveOutcome, SudokuSolver, TraceEvent},
+    units::Units,
+};
+
+// A board under construction, digits and candidates are stored as bitmasks
+#[derive(Debug, Clone)]
+struct Grid {
+    cells: Vec<u8>,
+    // The digits which can still be placed into every cell, empty for filled cells
+    candidates: Vec<u32>,
+    // The digits already placed in every unit
+    placed: Vec<u32>,
+    // Cells with a single candidate left, which still have to be filled in
+    singles: Vec<usize>,
+    // The number of filled cells
+    filled: usize,
+}
+
+fn bit(digit: u8) -> u32 {
+    1 << (digit - 1)
+}
+
+// How many guesses pass between checks of the clock and cancellation token. Every guess copies
+// and propagates a whole grid, so they are checked more often than in Algorithm X.
+const CLOCK_CHECK_INTERVAL: u64 = 64;
+
+/// A rule checked by the [`BacktrackingSolver`] on top of its units, like the cage sums of a
+/// killer sudoku. It is applied whenever propagating runs out of singles.
+pub(crate) trait Rule {
+    /// Removes the candidates of the empty cells which break the rule, given the digits placed
+    /// so far. Returns `None` if the rule is broken already, otherwise whether any candidate
+    /// was removed.
+    fn prune(&self, cells: &[u8], candidates: &mut [u32]) -> Option<bool>;
+}
+
+// The state shared by all levels of a search: its limits, the digits placed and guesses made
+// so far, why the search was stopped, who is tracing it and the rule it checks on top of the
+// units
+struct Context<'a> {
+    limits: &'a SearchLimits,
+    nodes: u64,
+    guesses: u64,
+    interruption: Option<Interruption>,
+    trace: Option<&'a mut dyn SearchObserver<TraceEvent>>,
+    rule: Option<&'a dyn Rule>,
+}
+
+static UNLIMITED: SearchLimits = SearchLimits {
+    deadline: None,
+    max_nodes: None,
+    cancel: None,
+};
+
+impl<'a> Context<'a> {
+    fn new(limits: &'a SearchLimits) -> Self {
+        Self {
+            limits,
+            nodes: 0,
+            guesses: 0,
+            interruption: None,
+            trace: None,
+            rule: None,
+        }
+    }
+
+    fn unlimited() -> Context<'static> {
+        Context::new(&UNLIMITED)
+    }
+
+    // Reports an event if the search is traced, the event is only created then. The search is
+    // cancelled if the observer stops it.
+    #[inline(always)]
+    fn emit(&mut self, event: impl FnOnce() -> TraceEvent) {
+        if let Some(trace) = &mut self.trace {
+            if self.interruption.is_none() && trace.on_event(event()).is_break() {
+                self.interruption = Some(Interruption::Cancelled);
+            }
+        }
+    }
+
+    // Counts a guess, returns false if the limits are exceeded or the observer stopped the
+    // search, and the search has to stop. The limits are only checked before guesses, so the
+    // singles filled in after the last guess may go past the node budget.
+    fn guess(&mut self) -> bool {
+        if self.interruption.is_some() {
+            return false;
+        }
+        if !self.limits.is_unlimited() {
+            let check_clock = self.guesses.is_multiple_of(CLOCK_CHECK_INTERVAL);
+            self.interruption = self.limits.check(self.nodes, check_clock);
+            if self.interruption.is_some() {
+                return false;
+            }
+        }
+        self.nodes += 1;
+        self.guesses += 1;
+        true
+    }
+}
+
+/// A backtracking solver working on bitmasks of the candidates.
+///
+/// Before every guess, naked and hidden singles are filled in until none are left. The search
+/// then branches on the empty cell with the fewest remaining candidates, which keeps the search
+/// tree small enough to compete with the [`AlgorithmXSudokuSolver`].
+///
+/// [`AlgorithmXSudokuSolver`]: crate::algorithm_x_solver::AlgorithmXSudokuSolver
+pub struct BacktrackingSolver {
+    // The units and peers of the boards the solver was set up for
+    units: Arc<Units>,
+}
 
 impl BacktrackingSolver {
-    fn solve_inplace(board: &mut Sudoku) -> bool {
-        let Some(idx) = board.iter().position(|elem| *elem == 0) else {
-            return true;
+    pub fn new() -> Self {
+        Self::for_size(BoardSize::CLASSIC)
+    }
+
+    /// Creates a solver for boards of the given size. Boards of other sizes can still be
+    /// solved, with the units of the board instead.
+    pub fn for_size(size: BoardSize) -> Self {
+        Self::with_variants(size, &[])
+    }
+
+    /// Creates a solver for boards of the given size with additional variant rules. Like for
+    /// the size, boards with other variants are solved with their own units.
+    pub fn with_variants(size: BoardSize, variants: &[Variant]) -> Self {
+        Self::build(size, variants, None)
+    }
+
+    /// Creates a solver for boards with the same size, variants and regions as the given one.
+    pub fn for_board(board: &Sudoku) -> Self {
+        Self {
+            units: Arc::clone(board.units()),
+        }
+    }
+
+    fn build(size: BoardSize, variants: &[Variant], regions: Option<&Regions>) -> Self {
+        let mut variants = variants.to_vec();
+        variants.sort_unstable();
+        variants.dedup();
+        Self {
+            units: Units::get(size, &variants, regions),
+        }
+    }
+
+    // Creates a solver with the given tables, which may have groups on top of the units of
+    // their boards
+    pub(crate) fn with_units(units: Units) -> Self {
+        Self {
+            units: Arc::new(units),
+        }
+    }
+
+    // Searches the board with the units of the solver and an additional rule, until `limit`
+    // solutions have been found. Returns their number.
+    pub(crate) fn search_with_rule(
+        &self,
+        board: &Sudoku,
+        limit: usize,
+        rule: &dyn Rule,
+        on_solution: impl FnMut(&[u8]),
+    ) -> usize {
+        let mut context = Context {
+            rule: Some(rule),
+            ..Context::new(&UNLIMITED)
+        };
+        self.search(board, limit, None, &mut context, on_solution)
+    }
+
+    /// Solves the board like [`SudokuSolver::solve`], and reports every placed digit, guess and
+    /// backtrack to the observer. The search ends as [`SolveOutcome::Cancelled`] if the
+    /// observer stops it.
+    pub fn trace(
+        &self,
+        board: Sudoku,
+        observer: &mut impl SearchObserver<TraceEvent>,
+    ) -> SolveOutcome {
+        if !self.fits(&board) {
+            return Self::for_board(&board).trace(board, observer);
+        }
+
+        let mut solution = None;
+        let mut context = Context {
+            trace: Some(observer),
+            ..Context::new(&UNLIMITED)
+        };
+        self.search(&board, 1, None, &mut context, |cells| {
+            solution = Some(cells.to_vec())
+        });
+        match (solution, context.interruption) {
+            (Some(cells), _) => SolveOutcome::Solved(self.fill(board, cells)),
+            (None, Some(interruption)) => interruption.into(),
+            (None, None) => SolveOutcome::NoSolution,
+        }
+    }
+
+    // Checks whether the units were set up for boards like the given one
+    fn fits(&self, board: &Sudoku) -> bool {
+        self.units.same_layout(board.units())
+    }
+
+    // The row and column of a cell, starting at 1
+    fn cell(&self, idx: usize) -> (usize, usize) {
+        let side = self.units.size().side();
+        (idx / side + 1, idx % side + 1)
+    }
+
+    fn all_digits(&self) -> u32 {
+        u32::MAX >> (u32::BITS as usize - self.units.size().side())
+    }
+
+    // Places the digits of a solution into the board
+    fn fill(&self, mut board: Sudoku, cells: Vec<u8>) -> Sudoku {
+        let side = self.units.size().side();
+        for (idx, digit) in cells.into_iter().enumerate() {
+            board.set(idx / side, idx % side, digit);
+        }
+        board
+    }
+
+    // Runs the search until `limit` solutions have been found and returns their number
+    fn search(
+        &self,
+        board: &Sudoku,
+        limit: usize,
+        mut metrics: Option<&mut SearchMetrics>,
+        context: &mut Context,
+        mut on_solution: impl FnMut(&[u8]),
+    ) -> usize {
+        if limit == 0 {
+            return 0;
+        }
+        let Some(grid) = self.init_grid(board) else {
+            return 0;
+        };
+        // The givens are placed like the rows of the givens are selected in Algorithm X
+        context.nodes += grid.filled as u64;
+        if let Some(metrics) = metrics.as_deref_mut() {
+            metrics.nodes += grid.filled as u64;
+            metrics.max_depth = metrics.max_depth.max(grid.filled);
+        }
+
+        let mut count = 0;
+        self.solve_grid(grid, metrics, context, &mut |cells| {
+            on_solution(cells);
+            count += 1;
+            count < limit
+        });
+        count
+    }
+
+    fn init_grid(&self, board: &Sudoku) -> Option<Grid> {
+        let n_cells = self.units.size().n_cells();
+        let mut grid = Grid {
+            cells: vec![0; n_cells],
+            candidates: vec![self.all_digits(); n_cells],
+            placed: vec![0; self.units.units().len()],
+            singles: Vec::new(),
+            filled: 0,
         };
+        for (idx, &cell) in board.iter().enumerate() {
+            if cell != 0 && !self.place(&mut grid, idx, cell) {
+                return None;
+            }
+        }
+        Some(grid)
+    }
+
+    // Places a digit and removes it from the candidates of the peers. Returns false if the
+    // digit can't be placed or a peer is left without candidates.
+    fn place(&self, grid: &mut Grid, idx: usize, digit: u8) -> bool {
+        if grid.candidates[idx] & bit(digit) == 0 {
+            return false;
+        }
+        grid.cells[idx] = digit;
+        grid.candidates[idx] = 0;
+        grid.filled += 1;
+        for &unit_idx in self.units.cell_units(idx) {
+            grid.placed[unit_idx] |= bit(digit);
+        }
+        for &peer in self.units.peers(idx) {
+            let candidates = &mut grid.candidates[peer];
+            if grid.cells[peer] != 0 || *candidates & bit(digit) == 0 {
+                continue;
+            }
+            *candidates &= !bit(digit);
+            match candidates.count_ones() {
+                0 => return false,
+                1 => grid.singles.push(peer),
+                _ => {}
+            }
+        }
+        true
+    }
 
-        let row = idx / 9;
-        let col = idx % 9;
+    // Fills in naked and hidden singles until none are left. Returns false on a contradiction.
+    fn propagate(&self, grid: &mut Grid, context: &mut Context) -> bool {
+        loop {
+            while let Some(idx) = grid.singles.pop() {
+                if grid.cells[idx] != 0 {
+                    continue;
+                }
+                let digit = grid.candidates[idx].trailing_zeros() as u8 + 1;
+                if !self.place(grid, idx, digit) {
+                    return false;
+                }
+                context.emit(|| {
+                    let (row, col) = self.cell(idx);
+                    TraceEvent::Place { row, col, digit }
+                });
+            }
+
+            let mut progress = false;
+            for (unit_idx, unit) in self.units.units().iter().enumerate() {
+                // The digits which are a candidate in at least one and in several cells
+                let (mut once, mut twice) = (0, 0);
+                for &idx in unit {
+                    twice |= once & grid.candidates[idx];
+                    once |= grid.candidates[idx];
+                }
+                if (once | grid.placed[unit_idx]) != self.all_digits() {
+                    return false;
+                }
 
-        for i in 1..10 {
-            if !board.is_valid(row, col, i) {
+                let mut hidden = once & !twice;
+                while hidden != 0 {
+                    let digit = hidden.trailing_zeros() as u8 + 1;
+                    hidden &= hidden - 1;
+                    let Some(&idx) = unit
+                        .iter()
+                        .find(|&&idx| grid.candidates[idx] & bit(digit) != 0)
+                    else {
+                        // An earlier single of this unit took the last place of the digit
+                        return false;
+                    };
+                    if !self.place(grid, idx, digit) {
+                        return false;
+                    }
+                    context.emit(|| {
+                        let (row, col) = self.cell(idx);
+                        TraceEvent::Place { row, col, digit }
+                    });
+                    progress = true;
+                }
+            }
+            if progress || !grid.singles.is_empty() {
                 continue;
             }
 
-            board.set(row, col, i);
-            if Self::solve_inplace(board) {
+            // The singles left by the rule are filled in by the next round
+            let Some(rule) = context.rule else {
                 return true;
+            };
+            match rule.prune(&grid.cells, &mut grid.candidates) {
+                None => return false,
+                Some(false) => return true,
+                Some(true) => {}
+            }
+            for (idx, &candidates) in grid.candidates.iter().enumerate() {
+                if grid.cells[idx] != 0 {
+                    continue;
+                }
+                match candidates.count_ones() {
+                    0 => return false,
+                    1 => grid.singles.push(idx),
+                    _ => {}
+                }
             }
-            board.set(row, col, 0);
         }
-        false
+    }
+
+    // Fills the remaining empty cells, calling `on_solution` for every solution until it
+    // returns false. Returns false if the search was stopped, by `on_solution` or the context.
+    // The digits the grid fills in are taken back once it returns true, and counted in the
+    // metrics if there are any.
+    fn solve_grid(
+        &self,
+        mut grid: Grid,
+        mut metrics: Option<&mut SearchMetrics>,
+        context: &mut Context,
+        on_solution: &mut impl FnMut(&[u8]) -> bool,
+    ) -> bool {
+        let timer = metrics.is_some().then(Instant::now);
+        let filled = grid.filled;
+        let consistent = self.propagate(&mut grid, context);
+        let singles = (grid.filled - filled) as u64;
+        context.nodes += singles;
+        if let (Some(metrics), Some(timer)) = (metrics.as_deref_mut(), timer) {
+            metrics.cover_time += timer.elapsed();
+            metrics.nodes += singles;
+            metrics.max_depth = metrics.max_depth.max(grid.filled);
+        }
+        let done = |metrics: Option<&mut SearchMetrics>| {
+            if let Some(metrics) = metrics {
+                metrics.backtracks += singles;
+            }
+            true
+        };
+        if !consistent {
+            return done(metrics);
+        }
+
+        // Choose the empty cell with the fewest candidates, after propagating there are at least
+        // two in every empty cell
+        let mut best: Option<(usize, u32)> = None;
+        for (idx, &candidates) in grid.candidates.iter().enumerate() {
+            if grid.cells[idx] != 0 {
+                continue;
+            }
+            if best.is_none_or(|(_, best)| candidates.count_ones() < best.count_ones()) {
+                best = Some((idx, candidates));
+            }
+            if candidates.count_ones() <= 2 {
+                break;
+            }
+        }
+
+        let Some((idx, mut candidates)) = best else {
+            context.emit(|| TraceEvent::Solution);
+            return on_solution(&grid.cells) && done(metrics);
+        };
+        if let Some(metrics) = metrics.as_deref_mut() {
+            metrics.column_selections[idx] += 1;
+        }
+        context.emit(|| TraceEvent::Choose {
+            column: self.column_name(idx),
+            count: candidates.count_ones() as usize,
+        });
+
+        while candidates != 0 {
+            let digit = candidates.trailing_zeros() as u8 + 1;
+            candidates &= candidates - 1;
+
+            if !context.guess() {
+                return false;
+            }
+            let timer = metrics.is_some().then(Instant::now);
+            let mut guess = grid.clone();
+            if let (Some(metrics), Some(timer)) = (metrics.as_deref_mut(), timer) {
+                metrics.uncover_time += timer.elapsed();
+                metrics.nodes += 1;
+                metrics.max_depth = metrics.max_depth.max(guess.filled + 1);
+            }
+            if self.place(&mut guess, idx, digit) {
+                context.emit(|| {
+                    let (row, col) = self.cell(idx);
+                    TraceEvent::Place { row, col, digit }
+                });
+                if !self.solve_grid(guess, metrics.as_deref_mut(), context, on_solution) {
+                    return false;
+                }
+                context.emit(|| {
+                    let (row, col) = self.cell(idx);
+                    TraceEvent::Backtrack { row, col, digit }
+                });
+            }
+            if let Some(metrics) = metrics.as_deref_mut() {
+                metrics.backtracks += 1;
+            }
+        }
+        done(metrics)
+    }
+}
+
+impl Default for BacktrackingSolver {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 impl SudokuSolver for BacktrackingSolver {
-    fn solve(&self, mut board: Sudoku) -> Option<Sudoku> {
-        Self::solve_inplace(&mut board).then_some(board)
+    fn solve(&self, board: Sudoku) -> Option<Sudoku> {
+        self.solve_with_limits(board, &UNLIMITED).solution()
+    }
+
+    fn solve_with_limits(&self, board: Sudoku, limits: &SearchLimits) -> SolveOutcome {
+        if !self.fits(&board) {
+            return Self::for_board(&board).solve_with_limits(board, limits);
+        }
+
+        let mut solution = None;
+        let mut context = Context::new(limits);
+        self.search(&board, 1, None, &mut context, |cells| {
+            solution = Some(cells.to_vec())
+        });
+        match (solution, context.interruption) {
+            (Some(cells), _) => SolveOutcome::Solved(self.fill(board, cells)),
+            (None, Some(interruption)) => interruption.into(),
+            (None, None) => SolveOutcome::NoSolution,
+        }
+    }
+
+    fn count_solutions(&self, board: &Sudoku, limit: usize) -> usize {
+        if !self.fits(board) {
+            return Self::for_board(board).count_solutions(board, limit);
+        }
+        let mut context = Context::unlimited();
+        self.search(board, limit, None, &mut context, |_| {})
+    }
+
+    // The columns of the selections are the cells branched on
+    fn solve_with_metrics(&self, board: Sudoku) -> (Option<Sudoku>, SearchMetrics) {
+        if !self.fits(&board) {
+            return Self::for_board(&board).solve_with_metrics(board);
+        }
+        let mut metrics = SearchMetrics {
+            column_selections: vec![0; self.units.size().n_cells()],
+            ..SearchMetrics::default()
+        };
+        let mut solution = None;
+        let mut context = Context::unlimited();
+        self.search(&board, 1, Some(&mut metrics), &mut context, |cells| {
+            solution = Some(cells.to_vec())
+        });
+        (solution.map(|cells| self.fill(board, cells)), metrics)
+    }
+
+    fn solve_and_count(
+        &self,
+        board: Sudoku,
+        limit: usize,
+        metrics: Option<&mut SearchMetrics>,
+    ) -> (Option<Sudoku>, usize) {
+        if !self.fits(&board) {
+            return Self::for_board(&board).solve_and_count(board, limit, metrics);
+        }
+        let metrics = metrics.map(|metrics| {
+            *metrics = SearchMetrics {
+                column_selections: vec![0; self.units.size().n_cells()],
+                ..SearchMetrics::default()
+            };
+            metrics
+        });
+        let mut solution = None;
+        let mut context = Context::unlimited();
+        let count = self.search(&board, limit, metrics, &mut context, |cells| {
+            solution.get_or_insert_with(|| cells.to_vec());
+        });
+        (solution.map(|cells| self.fill(board, cells)), count)
+    }
+
+    fn column_name(&self, col: usize) -> String {
+        let side = self.units.size().side();
+        format!("r{}c{}", col / side + 1, col % side + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sudoku_solver::Uniqueness;
+    use std::{
+        ops::ControlFlow,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    #[test]
+    fn test_solve() {
+        let solver = BacktrackingSolver::new();
+        let content = include_str!("../resources/bench_sudokus.txt");
+        for line in content.lines().take(10) {
+            let sudoku = Sudoku::try_from_str(line).unwrap();
+            let solution = solver.solve(sudoku.clone()).unwrap();
+            assert!(solution.is_solved());
+            assert!(sudoku
+                .iter()
+                .zip(solution.iter())
+                .all(|(given, digit)| *given == 0 || given == digit));
+        }
+    }
+
+    #[test]
+    fn test_count_solutions() {
+        let solver = BacktrackingSolver::new();
+        let unique = Sudoku::try_from_str(
+            "4.....8.5.3..........7......2.....6.....8.4......1.......6.3.7.5..2.....1.4......",
+        )
+        .unwrap();
+        assert_eq!(solver.count_solutions(&unique, 10), 1);
+        assert_eq!(solver.is_unique(&unique), Uniqueness::Unique);
+
+        let mut ambiguous = unique.clone();
+        ambiguous.set(0, 0, 0);
+        assert_eq!(solver.count_solutions(&ambiguous, 5), 5);
+        assert_eq!(solver.count_solutions(&ambiguous, 0), 0);
+        let (solution, count) = solver.solve_and_count(ambiguous.clone(), 2, None);
+        assert!(solution.unwrap().is_solved());
+        assert_eq!(count, 2);
+
+        let mut invalid = unique;
+        invalid.set(0, 1, 4);
+        assert_eq!(solver.is_unique(&invalid), Uniqueness::None);
+        assert!(solver.solve(invalid).is_none());
+
+        // All 288 4x4 sudokus
+        let empty = Sudoku::with_size(BoardSize::new(2, 2).unwrap());
+        assert_eq!(solver.count_solutions(&empty, 1000), 288);
+    }
+
+    #[test]
+    fn test_trace() {
+        let solver = BacktrackingSolver::new();
+        let sudoku = Sudoku::try_from_str(
+            "4.....8.5.3..........7......2.....6.....8.4......1.......6.3.7.5..2.....1.4......",
+        )
+        .unwrap();
+        let mut events = Vec::new();
+        let solution = solver.trace(sudoku.clone(), &mut events).solution();
+        assert_eq!(
+            solution.unwrap().serialize(),
+            solver.solve(sudoku.clone()).unwrap().serialize()
+        );
+        assert_eq!(events.last(), Some(&TraceEvent::Solution));
+
+        // Every guess follows the choice of its cell, and only guesses are taken back
+        assert!(events
+            .iter()
+            .any(|event| matches!(event, TraceEvent::Choose { .. })));
+        for (idx, event) in events.iter().enumerate() {
+            if let TraceEvent::Backtrack { row, col, digit } = event {
+                let guess = TraceEvent::Place {
+                    row: *row,
+                    col: *col,
+                    digit: *digit,
+                };
+                assert!(events[..idx].contains(&guess));
+            }
+        }
+
+        // Stopping at the first guess ends the search without reporting anything else
+        let mut events = Vec::new();
+        let mut observer = |event| {
+            let guess = matches!(event, TraceEvent::Choose { .. });
+            events.push(event);
+            match guess {
+                true => ControlFlow::Break(()),
+                false => ControlFlow::Continue(()),
+            }
+        };
+        let outcome = solver.trace(sudoku, &mut observer);
+        assert!(matches!(outcome, SolveOutcome::Cancelled));
+        assert!(matches!(events.last(), Some(TraceEvent::Choose { .. })));
+    }
+
+    #[test]
+    fn test_solve_with_limits() {
+        let solver = BacktrackingSolver::new();
+        // Needs guesses, the singles alone don't solve it
+        let sudoku = Sudoku::try_from_str(
+            "4.....8.5.3..........7......2.....6.....8.4......1.......6.3.7.5..2.....1.4......",
+        )
+        .unwrap();
+        let cancel = Arc::new(AtomicBool::new(true));
+        let cancelled = SearchLimits {
+            cancel: Some(cancel.clone()),
+            ..SearchLimits::default()
+        };
+        let node_limit = SearchLimits {
+            max_nodes: Some(1),
+            ..SearchLimits::default()
+        };
+
+        assert!(matches!(
+            solver.solve_with_limits(sudoku.clone(), &cancelled),
+            SolveOutcome::Cancelled
+        ));
+        assert!(matches!(
+            solver.solve_with_limits(sudoku.clone(), &node_limit),
+            SolveOutcome::NodeLimit
+        ));
+        assert!(matches!(
+            solver.solve_with_limits(sudoku.clone(), &SearchLimits::timeout(Duration::ZERO)),
+            SolveOutcome::TimedOut
+        ));
+
+        cancel.store(false, Ordering::Relaxed);
+        let solution = solver
+            .solve_with_limits(sudoku.clone(), &cancelled)
+            .solution();
+        assert!(solution.unwrap().is_solved());
+
+        // Contradicting givens have no solution, whatever the limits
+        let mut contradicting = sudoku;
+        contradicting.set(0, 1, 4);
+        assert!(matches!(
+            solver.solve_with_limits(contradicting, &node_limit),
+            SolveOutcome::NoSolution
+        ));
+    }
+
+    #[test]
+    fn test_solve_with_metrics() {
+        let solver = BacktrackingSolver::new();
+        let sudoku = Sudoku::try_from_str(
+            "4.....8.5.3..........7......2.....6.....8.4......1.......6.3.7.5..2.....1.4......",
+        )
+        .unwrap();
+        let (solution, metrics) = solver.solve_with_metrics(sudoku.clone());
+        assert_eq!(
+            solution.unwrap().serialize(),
+            solver.solve(sudoku).unwrap().serialize()
+        );
+        // Like in Algorithm X, the digits of the solution are the nodes which are still in
+        // place, the givens included
+        assert_eq!(metrics.max_depth, 81);
+        assert_eq!(metrics.nodes - metrics.backtracks, 81);
+        let selections = metrics.column_selections.iter().sum::<u64>();
+        assert!(selections > 0 && selections < metrics.nodes);
+        assert_eq!(solver.column_name(10), "r2c2");
+    }
+
+    #[test]
+    fn test_variants_and_regions() {
+        let solver = BacktrackingSolver::new();
+
+        let mut sudoku = Sudoku::try_from_str(
+            "1.3.5.7.9.7.8.9.1.8.4.2.3.5.5.3.4.9.4.6.8.5.7.8.5.2.4.5.2.1......................",
+        )
+        .unwrap();
+        sudoku.set_variants(&[Variant::Diagonal, Variant::AntiKing]);
+        assert_eq!(solver.is_unique(&sudoku), Uniqueness::Unique);
+        assert!(solver.solve(sudoku).unwrap().is_solved());
+
+        let jigsaw =
+            Sudoku::try_from_jigsaw_str(include_str!("../resources/jigsaw_sudoku.txt")).unwrap();
+        assert_eq!(solver.is_unique(&jigsaw), Uniqueness::Unique);
+        assert!(solver.solve(jigsaw).unwrap().is_solved());
     }
 }
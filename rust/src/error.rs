@@ -0,0 +1,116 @@
+use std::{fmt, io, path::PathBuf};
+use thiserror::Error;
+
+use crate::sudoku::{BoardSize, Conflict};
+
+pub type Result<T, E = SudokuError> = std::result::Result<T, E>;
+
+/// The errors of reading, parsing and checking sudokus.
+#[derive(Debug, Error)]
+pub enum SudokuError {
+    #[error("Invalid sudoku string length: expected {expected}, got {got}")]
+    InvalidLength { expected: usize, got: usize },
+    #[error("Invalid sudoku string length: expected a square number of cells like 81, got {0}")]
+    InvalidCellCount(usize),
+    /// A character which is neither a digit of the board nor a blank, the column starts at 1
+    #[error(
+        "Invalid character '{character}' at column {column}: expected a digit of a {size} sudoku \
+         or one of the blanks 0 . _ *"
+    )]
+    InvalidCharacter {
+        character: char,
+        column: usize,
+        size: BoardSize,
+    },
+    #[error("Invalid value {value} at r{}c{}: expected 0 for a blank or a digit up to {max}", .row + 1, .col + 1)]
+    ValueOutOfRange {
+        row: usize,
+        col: usize,
+        value: u8,
+        max: u8,
+    },
+    /// The givens contradict each other, see [`crate::sudoku::Sudoku::validate`]
+    #[error(
+        "Contradictory sudoku: {}",
+        .0.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ")
+    )]
+    ConflictingGivens(Vec<Conflict>),
+    #[error(
+        "Invalid box size {box_rows}x{box_cols}: boxes must have between 1 and {} cells",
+        BoardSize::MAX_SIDE
+    )]
+    InvalidBoxSize { box_rows: usize, box_cols: usize },
+    #[error("Invalid box size '{0}': expected something like 3x3")]
+    InvalidBoxSizeFormat(String),
+    /// A name which isn't one of the values of an option, like a variant or a format
+    #[error("Unknown {kind} '{name}': expected one of {expected}")]
+    UnknownName {
+        kind: &'static str,
+        name: String,
+        expected: String,
+    },
+    #[error("The region map is for a board with {regions} rows, but the sudoku has {sudoku}")]
+    RegionsMismatch { regions: usize, sudoku: usize },
+    #[error("Invalid region map: {0}")]
+    InvalidRegions(String),
+    #[error("Invalid pencil marks: {0}")]
+    InvalidPencilMarks(String),
+    #[error("Invalid grid: {0}")]
+    InvalidGrid(String),
+    #[error("Invalid cage '{cage}': {reason}")]
+    InvalidCage { cage: String, reason: String },
+    #[error("Invalid cell '{0}': expected something like r1c1")]
+    InvalidCell(String),
+    #[error("Invalid killer sudoku: {0}")]
+    InvalidKillerSudoku(String),
+    #[error("No sudoku found")]
+    NoSudoku,
+    #[error("Expected a single sudoku, found another one at line {0}")]
+    MultipleSudokus(usize),
+    /// An error on a line of a text, the line starts at 1
+    #[error("line {line}: {error}")]
+    AtLine {
+        line: usize,
+        error: Box<SudokuError>,
+    },
+    /// An error on a line of a file, the line starts at 1
+    #[error("{}:{line}: {error}", .path.display())]
+    InFile {
+        path: PathBuf,
+        line: usize,
+        error: Box<SudokuError>,
+    },
+    #[error("Failed to read {}", .path.display())]
+    Io { path: PathBuf, source: io::Error },
+}
+
+impl SudokuError {
+    // Finds the value displayed as `name`, for parsing the enums of the crate
+    pub(crate) fn parse_name<T: Copy + fmt::Display>(
+        name: &str,
+        kind: &'static str,
+        values: &[T],
+    ) -> Result<T> {
+        values
+            .iter()
+            .copied()
+            .find(|value| value.to_string() == name)
+            .ok_or_else(|| SudokuError::UnknownName {
+                kind,
+                name: name.to_string(),
+                expected: values
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            })
+    }
+
+    /// The error without the line or file it was found in.
+    pub fn inner(&self) -> &SudokuError {
+        match self {
+            SudokuError::AtLine { error, .. } | SudokuError::InFile { error, .. } => error.inner(),
+            error => error,
+        }
+    }
+}
@@ -0,0 +1,308 @@
+use std::{fmt, path::Path, str::FromStr};
+
+use crate::{
+    error::{Result, SudokuError},
+    sudoku::{BoardSize, Sudoku},
+};
+
+/// The text formats sudokus are read from and written in.
+///
+/// All formats take `0`, `.`, `_` or `*` as blanks and skip comments starting with `#`. Grids
+/// may be surrounded by blank lines, and a file can hold several of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// A single line per sudoku, like `4.....8.5.3...`
+    Line,
+    /// A grid with `|` between boxes and `---+---+---` between bands, as written by `Display`
+    Grid,
+    /// SadMan Software's `.sdk`, a row per line without any separators
+    Sdk,
+    /// Simple Sudoku's `.ss`, a grid with `|` between boxes and `-----------` between bands
+    Ss,
+}
+
+impl Format {
+    pub const ALL: [Format; 4] = [Format::Line, Format::Grid, Format::Sdk, Format::Ss];
+
+    /// The format of a file with the extension `.sdk` or `.ss`.
+    pub fn from_extension(path: &Path) -> Option<Format> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "sdk" => Some(Format::Sdk),
+            "ss" => Some(Format::Ss),
+            _ => None,
+        }
+    }
+
+    /// Detects the format from the first sudoku of a text.
+    ///
+    /// Rows with `|` or lines of dashes make a grid, a `.ss` grid if the dashes aren't broken up
+    /// by `+`. Without separators the text is a `.sdk` grid if it has as many lines as the first
+    /// one has cells, otherwise it holds a sudoku per line. As 16 cells also fit a 4x4 sudoku on
+    /// a single line, 16x16 `.sdk` grids can't be detected.
+    pub fn detect(content: &str) -> Format {
+        let rows: Vec<&str> = content_lines(content)
+            .map(|(_, line)| line)
+            .skip_while(|line| line.trim().is_empty())
+            .take_while(|line| !line.trim().is_empty())
+            .collect();
+
+        if rows
+            .iter()
+            .any(|row| is_separator(row) || row.contains('|'))
+        {
+            return match rows
+                .iter()
+                .any(|row| is_separator(row) && row.contains('+'))
+            {
+                true => Format::Grid,
+                false => Format::Ss,
+            };
+        }
+
+        let width = rows.first().map_or(0, |row| cells(row).count());
+        let is_grid = rows.len() >= width
+            && BoardSize::from_n_cells(width).is_none()
+            && BoardSize::from_n_cells(width * width).is_some();
+        match is_grid {
+            true => Format::Sdk,
+            false => Format::Line,
+        }
+    }
+
+    /// Parses a text with a single sudoku in this format.
+    pub fn parse(&self, s: &str) -> Result<Sudoku> {
+        let mut sudokus = parse_all(s, *self).into_iter();
+        let (line, sudoku) = match (sudokus.next(), sudokus.next()) {
+            (Some(sudoku), None) => sudoku,
+            (None, _) => return Err(SudokuError::NoSudoku),
+            (Some(_), Some((line, _))) => return Err(SudokuError::MultipleSudokus(line)),
+        };
+        match self {
+            Format::Line => sudoku,
+            _ => sudoku.map_err(|error| SudokuError::AtLine {
+                line,
+                error: Box::new(error),
+            }),
+        }
+    }
+
+    /// Writes a sudoku in this format, every line ends with a newline.
+    pub fn write(&self, sudoku: &Sudoku) -> String {
+        match self {
+            Format::Line => format!("{}\n", sudoku.serialize()),
+            Format::Grid => sudoku.to_string(),
+            Format::Sdk => sudoku
+                .rows()
+                .map(|row| {
+                    let mut line: String =
+                        row.iter().map(|d| BoardSize::digit_to_char(*d)).collect();
+                    line.push('\n');
+                    line
+                })
+                .collect(),
+            Format::Ss => sudoku
+                .to_string()
+                .lines()
+                .map(|line| match is_separator(line) {
+                    true => format!("{}\n", "-".repeat(line.len())),
+                    false => format!("{}\n", line),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Format::Line => write!(f, "line"),
+            Format::Grid => write!(f, "grid"),
+            Format::Sdk => write!(f, "sdk"),
+            Format::Ss => write!(f, "ss"),
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = SudokuError;
+
+    /// Parses a format by its name, like `line` or `sdk`.
+    fn from_str(s: &str) -> Result<Format> {
+        SudokuError::parse_name(s, "format", &Format::ALL)
+    }
+}
+
+/// Parses a single sudoku in any of the formats, see [`Format::detect`].
+pub fn parse_sudoku(s: &str) -> Result<Sudoku> {
+    Format::detect(s).parse(s)
+}
+
+/// Parses all sudokus of a text in the given format. Every sudoku comes with the number of the
+/// line it starts on, or for invalid ones the line the error was found on.
+pub(crate) fn parse_all(content: &str, format: Format) -> Vec<(usize, Result<Sudoku>)> {
+    if format == Format::Line {
+        return content_lines(content)
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(number, line)| {
+                let indent = line.chars().take_while(|c| c.is_whitespace()).count();
+                (number, Sudoku::parse_line(line.trim(), None, indent + 1))
+            })
+            .collect();
+    }
+
+    // A grid ends at a blank line, or once it has as many rows as the first row has cells
+    let mut sudokus = Vec::new();
+    let mut rows = Vec::new();
+    for (number, line) in content_lines(content).filter(|(_, line)| !is_separator(line)) {
+        if !line.trim().is_empty() {
+            rows.push((number, line));
+        }
+        let complete = rows
+            .first()
+            .is_some_and(|(_, row)| rows.len() == cells(row).count());
+        if !rows.is_empty() && (complete || line.trim().is_empty()) {
+            sudokus.push(parse_grid(&rows));
+            rows.clear();
+        }
+    }
+    if !rows.is_empty() {
+        sudokus.push(parse_grid(&rows));
+    }
+    sudokus
+}
+
+// The numbered lines of a text without comments. Lines with nothing but a comment are left
+// out, blank lines are kept as they end grids.
+fn content_lines(content: &str) -> impl Iterator<Item = (usize, &str)> {
+    content.lines().enumerate().filter_map(|(idx, line)| {
+        let (line, comment) = match line.split_once('#') {
+            Some((line, _)) => (line, true),
+            None => (line, false),
+        };
+        (!comment || !line.trim().is_empty()).then_some((idx + 1, line.trim_end()))
+    })
+}
+
+// Whether a line separates the bands of a grid, like `---+---+---` or `-----------`
+fn is_separator(line: &str) -> bool {
+    let line = line.trim();
+    line.contains('-') && line.chars().all(|c| "-+|".contains(c))
+}
+
+// The cells of a grid row with their columns, starting at 1
+fn cells(row: &str) -> impl Iterator<Item = (usize, char)> + '_ {
+    (1..)
+        .zip(row.chars())
+        .filter(|(_, c)| !c.is_whitespace() && *c != '|')
+}
+
+// Parses the numbered rows of a grid, the size follows from the number of cells in a row
+fn parse_grid(rows: &[(usize, &str)]) -> (usize, Result<Sudoku>) {
+    let (first, _) = rows[0];
+    let side = cells(rows[0].1).count();
+    let Some(size) = BoardSize::from_n_cells(side * side) else {
+        let error =
+            SudokuError::InvalidGrid(format!("a row of {} cells doesn't fit a sudoku", side));
+        return (first, Err(error));
+    };
+    if rows.len() != side {
+        let error = SudokuError::InvalidGrid(format!("expected {} rows, got {}", side, rows.len()));
+        return (first, Err(error));
+    }
+
+    let mut board = String::with_capacity(size.n_cells());
+    for &(number, row) in rows {
+        let n_cells = cells(row).count();
+        if n_cells != side {
+            let error = SudokuError::InvalidGrid(format!(
+                "expected {} cells in a row, got {}",
+                side, n_cells
+            ));
+            return (number, Err(error));
+        }
+        for (column, c) in cells(row) {
+            if size.char_to_digit(c).is_none() {
+                let error = SudokuError::InvalidCharacter {
+                    character: c,
+                    column,
+                    size,
+                };
+                return (number, Err(error));
+            }
+            board.push(c);
+        }
+    }
+    (first, Sudoku::try_from_str_with_size(&board, size))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LINE: &str =
+        "4.....8.5.3..........7......2.....6.....8.4......1.......6.3.7.5..2.....1.4......";
+
+    #[test]
+    fn test_formats() -> Result<()> {
+        let sudoku = Sudoku::try_from_str(LINE)?;
+
+        for format in [Format::Line, Format::Grid, Format::Sdk, Format::Ss] {
+            let written = format.write(&sudoku);
+            assert_eq!(Format::detect(&written), format);
+            assert_eq!(format.parse(&written)?.serialize(), LINE);
+            assert_eq!(parse_sudoku(&written)?.serialize(), LINE);
+        }
+
+        assert!(Format::Ss.write(&sudoku).contains("\n-----------\n"));
+        assert!(Format::Sdk
+            .write(&sudoku)
+            .starts_with("4.....8.5\n.3.......\n"));
+
+        // A 4x4 grid with comments, spaces between the cells and borders
+        let grid = "
+#A some author
++-----+-----+
+| 1 . | 3 _ |  # first row
+| * . | 2 . |
++-----+-----+
+| 3 4 | . . |
+| . . | 4 . |
++-----+-----+
+";
+        assert_eq!(Format::detect(grid), Format::Grid);
+        assert_eq!(parse_sudoku(grid)?.serialize(), "1.3...2.34....4.");
+
+        let err = Format::Grid.parse(&grid.replace('*', "x")).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 5: Invalid character 'x' at column 3: expected a digit of a 2x2 sudoku or one \
+             of the blanks 0 . _ *"
+        );
+        let err = Format::Sdk.parse("1.3.\n..2.\n34..\n").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 1: Invalid grid: expected 4 rows, got 3"
+        );
+        let err = Format::Sdk.parse("1.3.\n..2\n34..\n..4.\n").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 2: Invalid grid: expected 4 cells in a row, got 3"
+        );
+
+        // Grids following each other directly, and a 16x16 sdk grid which can't be detected
+        let two = format!(
+            "{}{}",
+            Format::Sdk.write(&sudoku),
+            Format::Sdk.write(&sudoku)
+        );
+        assert_eq!(parse_all(&two, Format::Sdk).len(), 2);
+        let big = Sudoku::with_size(BoardSize::new(4, 4)?);
+        assert_eq!(Format::detect(&Format::Sdk.write(&big)), Format::Line);
+        assert_eq!(
+            Format::Sdk.parse(&Format::Sdk.write(&big))?.size(),
+            big.size()
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,504 @@
+use crate::{
+    logical_solver::{LogicalSolver, Step},
+    sudoku::Sudoku,
+    sudoku_solver::TraceEvent,
+};
+
+/// The state of a sudoku being played: the digits and pencil marks entered by the player, a
+/// cursor, and the history of edits for undo and redo.
+///
+/// All coordinates are zero based `(row, col)` pairs.
+pub struct Game {
+    givens: Sudoku,
+    board: Sudoku,
+    // The solution the entries are checked against, if the puzzle has one
+    solution: Option<Sudoku>,
+    logical: LogicalSolver,
+    // The pencil marks of every cell in row major order, bit `d - 1` is set for the digit `d`
+    marks: Vec<u32>,
+    cursor: (usize, usize),
+    undo: Vec<Edit>,
+    redo: Vec<Edit>,
+}
+
+// The content of a cell which can be edited
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Cell {
+    digit: u8,
+    marks: u32,
+}
+
+// A single change of a cell, which can be undone and redone
+#[derive(Debug, Clone, Copy)]
+struct Edit {
+    row: usize,
+    col: usize,
+    before: Cell,
+    after: Cell,
+}
+
+/// Help for the player, see [`Game::hint`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Hint {
+    /// An entered digit doesn't match the solution
+    Mistake { row: usize, col: usize, digit: u8 },
+    /// A deduction which can be made on the board
+    Step(Step),
+    /// None of the known techniques applies, so the digit of a cell is given away
+    Reveal { row: usize, col: usize, digit: u8 },
+    /// All cells are filled correctly
+    Solved,
+    /// The puzzle has no solution
+    NoSolution,
+}
+
+impl Game {
+    /// Starts a game of the puzzle. The solution is the one entries are checked against, if
+    /// the puzzle has more than one, any of them can be used.
+    pub fn new(puzzle: Sudoku, solution: Option<Sudoku>) -> Self {
+        let n_cells = puzzle.size().n_cells();
+        Self {
+            logical: LogicalSolver::for_board(&puzzle),
+            board: puzzle.clone(),
+            givens: puzzle,
+            solution,
+            marks: vec![0; n_cells],
+            cursor: (0, 0),
+            undo: Vec::new(),
+            redo: Vec::new(),
+        }
+    }
+
+    /// The board with the givens and the digits entered so far.
+    pub fn board(&self) -> &Sudoku {
+        &self.board
+    }
+
+    pub fn solution(&self) -> Option<&Sudoku> {
+        self.solution.as_ref()
+    }
+
+    pub fn is_given(&self, row: usize, col: usize) -> bool {
+        self.givens.get(row, col) != 0
+    }
+
+    /// The pencil marks of a cell as a bitmask, bit `d - 1` is set if `d` is marked.
+    pub fn marks(&self, row: usize, col: usize) -> u32 {
+        self.marks[row * self.side() + col]
+    }
+
+    pub fn cursor(&self) -> (usize, usize) {
+        self.cursor
+    }
+
+    /// Moves the cursor by the given number of rows and columns, wrapping around the edges.
+    pub fn move_cursor(&mut self, d_row: isize, d_col: isize) {
+        let side = self.side() as isize;
+        let (row, col) = self.cursor;
+        self.cursor = (
+            (row as isize + d_row).rem_euclid(side) as usize,
+            (col as isize + d_col).rem_euclid(side) as usize,
+        );
+    }
+
+    /// Enters a digit into the cell under the cursor, 0 clears it. Returns whether the board
+    /// changed, givens can't be changed.
+    pub fn enter(&mut self, digit: u8) -> bool {
+        let (row, col) = self.cursor;
+        if self.is_given(row, col) || digit as usize > self.side() {
+            return false;
+        }
+        let before = self.cell(row, col);
+        self.edit(row, col, Cell { digit, ..before })
+    }
+
+    /// Toggles a pencil mark of the empty cell under the cursor, returns whether it changed.
+    pub fn toggle_mark(&mut self, digit: u8) -> bool {
+        let (row, col) = self.cursor;
+        let before = self.cell(row, col);
+        if before.digit != 0 || !(1..=self.side() as u8).contains(&digit) {
+            return false;
+        }
+        let marks = before.marks ^ (1 << (digit - 1));
+        self.edit(row, col, Cell { marks, ..before })
+    }
+
+    /// Takes back the last edit and moves the cursor to its cell, returns whether there was
+    /// one.
+    pub fn undo(&mut self) -> bool {
+        let Some(edit) = self.undo.pop() else {
+            return false;
+        };
+        self.set_cell(edit.row, edit.col, edit.before);
+        self.cursor = (edit.row, edit.col);
+        self.redo.push(edit);
+        true
+    }
+
+    /// Makes the last undone edit again and moves the cursor to its cell, returns whether
+    /// there was one.
+    pub fn redo(&mut self) -> bool {
+        let Some(edit) = self.redo.pop() else {
+            return false;
+        };
+        self.set_cell(edit.row, edit.col, edit.after);
+        self.cursor = (edit.row, edit.col);
+        self.undo.push(edit);
+        true
+    }
+
+    /// The filled cells whose digit breaks a rule, because it can't be placed there given
+    /// the other digits according to [`Sudoku::is_valid`].
+    pub fn conflicts(&self) -> Vec<(usize, usize)> {
+        let side = self.side();
+        let mut board = self.board.clone();
+        let mut conflicts = Vec::new();
+        for (row, col) in (0..side).flat_map(|row| (0..side).map(move |col| (row, col))) {
+            let digit = board.get(row, col);
+            if digit == 0 {
+                continue;
+            }
+            board.set(row, col, 0);
+            if !board.is_valid(row, col, digit) {
+                conflicts.push((row, col));
+            }
+            board.set(row, col, digit);
+        }
+        conflicts
+    }
+
+    /// Whether all cells are filled and no rule is broken.
+    pub fn is_solved(&self) -> bool {
+        self.board.is_solved()
+    }
+
+    /// Finds the most helpful hint for the current board.
+    ///
+    /// Entered digits which don't match the solution are pointed out first. Otherwise the
+    /// easiest deduction of the logical solver is suggested, and if there is none, the
+    /// digit of the cell under the cursor, or of the empty cell with the fewest candidates,
+    /// is revealed.
+    pub fn hint(&self) -> Hint {
+        let Some(solution) = &self.solution else {
+            return Hint::NoSolution;
+        };
+        let side = self.side();
+        let cells = || (0..side).flat_map(|row| (0..side).map(move |col| (row, col)));
+
+        if let Some((row, col)) = cells().find(|&(row, col)| {
+            let digit = self.board.get(row, col);
+            digit != 0 && digit != solution.get(row, col)
+        }) {
+            let digit = self.board.get(row, col);
+            return Hint::Mistake { row, col, digit };
+        }
+        if cells().all(|(row, col)| self.board.get(row, col) != 0) {
+            return Hint::Solved;
+        }
+        if let Some(step) = self.logical.next_step(&self.board) {
+            return Hint::Step(step);
+        }
+
+        let (row, col) = match self.cursor {
+            (row, col) if self.board.get(row, col) == 0 => (row, col),
+            _ => cells()
+                .filter(|&(row, col)| self.board.get(row, col) == 0)
+                .min_by_key(|&(row, col)| self.board.candidates(row, col).count_ones())
+                .unwrap(),
+        };
+        let digit = solution.get(row, col);
+        Hint::Reveal { row, col, digit }
+    }
+
+    fn side(&self) -> usize {
+        self.board.size().side()
+    }
+
+    fn cell(&self, row: usize, col: usize) -> Cell {
+        Cell {
+            digit: self.board.get(row, col),
+            marks: self.marks(row, col),
+        }
+    }
+
+    fn set_cell(&mut self, row: usize, col: usize, cell: Cell) {
+        self.board.set(row, col, cell.digit);
+        self.marks[row * self.board.size().side() + col] = cell.marks;
+    }
+
+    // Changes a cell and records the change, a new edit can't be redone after undoing
+    fn edit(&mut self, row: usize, col: usize, after: Cell) -> bool {
+        let before = self.cell(row, col);
+        if before == after {
+            return false;
+        }
+        self.set_cell(row, col, after);
+        self.undo.push(Edit {
+            row,
+            col,
+            before,
+            after,
+        });
+        self.redo.clear();
+        true
+    }
+}
+
+/// Replays the [`TraceEvent`]s of a solver on the puzzle, to step through how it was solved.
+#[derive(Debug, Clone)]
+pub struct SolutionPath {
+    puzzle: Sudoku,
+    events: Vec<TraceEvent>,
+    // The number of events applied to the board
+    step: usize,
+    board: Sudoku,
+    // The cells filled by the solver so far, in the order they were filled
+    placed: Vec<(usize, usize)>,
+}
+
+impl SolutionPath {
+    pub fn new(puzzle: Sudoku, events: Vec<TraceEvent>) -> Self {
+        Self {
+            board: puzzle.clone(),
+            puzzle,
+            events,
+            step: 0,
+            placed: Vec::new(),
+        }
+    }
+
+    pub fn events(&self) -> &[TraceEvent] {
+        &self.events
+    }
+
+    /// The number of events replayed so far.
+    pub fn step(&self) -> usize {
+        self.step
+    }
+
+    /// The board after the events replayed so far.
+    pub fn board(&self) -> &Sudoku {
+        &self.board
+    }
+
+    /// The event replayed last, if any.
+    pub fn current(&self) -> Option<&TraceEvent> {
+        self.step.checked_sub(1).map(|idx| &self.events[idx])
+    }
+
+    /// Replays the next event, returns whether there was one.
+    pub fn forward(&mut self) -> bool {
+        if self.step == self.events.len() {
+            return false;
+        }
+        self.apply(self.step);
+        self.step += 1;
+        true
+    }
+
+    /// Takes back the event replayed last, returns whether there was one.
+    pub fn back(&mut self) -> bool {
+        match self.step.checked_sub(1) {
+            Some(step) => {
+                self.go_to(step);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Replays the events up to the given step, or all of them if there are fewer.
+    pub fn go_to(&mut self, step: usize) {
+        // A backtrack can't be taken back, so going back starts over from the puzzle
+        if step < self.step {
+            self.board = self.puzzle.clone();
+            self.placed.clear();
+            self.step = 0;
+        }
+        while self.step < step && self.forward() {}
+    }
+
+    fn apply(&mut self, idx: usize) {
+        match self.events[idx] {
+            TraceEvent::Place { row, col, digit } => {
+                let (row, col) = (row - 1, col - 1);
+                if self.puzzle.get(row, col) == 0 {
+                    self.board.set(row, col, digit);
+                    self.placed.push((row, col));
+                }
+            }
+            // The digits placed after the one taken back go with it
+            TraceEvent::Backtrack { row, col, .. } => {
+                if self.puzzle.get(row - 1, col - 1) == 0 {
+                    while let Some((r, c)) = self.placed.pop() {
+                        self.board.set(r, c, 0);
+                        if (r, c) == (row - 1, col - 1) {
+                            break;
+                        }
+                    }
+                }
+            }
+            TraceEvent::Choose { .. } | TraceEvent::Cover { .. } | TraceEvent::Solution => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        algorithm_x_solver::AlgorithmXSudokuSolver, backtracking_solver::BacktrackingSolver,
+        error::Result, logical_solver::Technique, sudoku_solver::SudokuSolver,
+    };
+
+    const PUZZLE: &str =
+        "4.....8.5.3..........7......2.....6.....8.4......1.......6.3.7.5..2.....1.4......";
+
+    fn new_game(puzzle: &str) -> Result<Game> {
+        let sudoku = Sudoku::try_from_str(puzzle)?;
+        let solution = AlgorithmXSudokuSolver::new().solve(sudoku.clone());
+        Ok(Game::new(sudoku, solution))
+    }
+
+    #[test]
+    fn test_game() -> Result<()> {
+        let mut game = new_game(PUZZLE)?;
+
+        // Givens can't be changed
+        assert!(!game.enter(1));
+        game.move_cursor(0, -1);
+        assert_eq!(game.cursor(), (0, 8));
+        assert!(!game.enter(1));
+
+        game.move_cursor(0, -1);
+        assert!(game.toggle_mark(1));
+        assert!(game.toggle_mark(6));
+        assert!(game.toggle_mark(1));
+        assert_eq!(game.marks(0, 7), 0b100000);
+
+        // The marks are kept while the cell is filled
+        assert!(game.enter(4));
+        assert!(!game.enter(4));
+        assert!(!game.toggle_mark(2));
+        assert_eq!(game.board().get(0, 7), 4);
+        assert_eq!(game.conflicts(), vec![(0, 0), (0, 7)]);
+
+        game.move_cursor(-1, 0);
+        assert!(game.enter(6));
+        assert_eq!(game.cursor(), (8, 7));
+        assert!(game.undo());
+        assert!(game.undo());
+        assert_eq!(game.cursor(), (0, 7));
+        assert_eq!(game.board().get(0, 7), 0);
+        assert_eq!(game.marks(0, 7), 0b100000);
+        assert!(game.conflicts().is_empty());
+
+        assert!(game.redo());
+        assert_eq!(game.board().get(0, 7), 4);
+        assert!(game.enter(0));
+        assert!(!game.redo());
+        assert_eq!(game.board().get(8, 7), 0);
+
+        while game.undo() {}
+        assert_eq!(game.board().serialize(), PUZZLE);
+        assert_eq!(game.marks(0, 7), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_hint() -> Result<()> {
+        let mut game = new_game(
+            "..9.....3.....9...7.....5.6..65..4.....3......28......3..75.6..6...........12.3.8",
+        )?;
+        let solution = game.solution().unwrap().clone();
+
+        let Hint::Step(step) = game.hint() else {
+            panic!("expected a step");
+        };
+        assert_eq!(step.technique, Technique::HiddenSingle);
+        let (row, col, digit) = step.placements[0];
+        game.cursor = (row, col);
+        game.enter(digit % 9 + 1);
+        assert_eq!(
+            game.hint(),
+            Hint::Mistake {
+                row,
+                col,
+                digit: digit % 9 + 1
+            }
+        );
+
+        for row in 0..9 {
+            for col in 0..9 {
+                game.cursor = (row, col);
+                game.enter(solution.get(row, col));
+            }
+        }
+        assert!(game.is_solved());
+        assert_eq!(game.hint(), Hint::Solved);
+
+        // Logic alone gets stuck on this one
+        let sudoku = Sudoku::try_from_str(
+            "48.3............71.2.......7.5....6....2..8.............1.76...3.....4......5....",
+        )?;
+        let solution = AlgorithmXSudokuSolver::new().solve(sudoku.clone());
+        let stuck = LogicalSolver::for_board(&sudoku).solve_with_steps(sudoku);
+        let mut game = Game::new(stuck.board, solution.clone());
+        let Hint::Reveal { row, col, digit } = game.hint() else {
+            panic!("expected a revealed digit");
+        };
+        assert_eq!(game.board().get(row, col), 0);
+        assert_eq!(digit, solution.as_ref().unwrap().get(row, col));
+        // The cell under the cursor is preferred
+        let (row, col) = (0..81)
+            .rev()
+            .map(|idx| (idx / 9, idx % 9))
+            .find(|&(row, col)| game.board().get(row, col) == 0)
+            .unwrap();
+        game.cursor = (row, col);
+        let digit = solution.unwrap().get(row, col);
+        assert_eq!(game.hint(), Hint::Reveal { row, col, digit });
+
+        let game = new_game(
+            "11...............................................................................",
+        )?;
+        assert_eq!(game.hint(), Hint::NoSolution);
+        Ok(())
+    }
+
+    #[test]
+    fn test_solution_path() -> Result<()> {
+        let sudoku = Sudoku::try_from_str(PUZZLE)?;
+        let mut events = Vec::new();
+        let solution = AlgorithmXSudokuSolver::new()
+            .trace(sudoku.clone(), &mut events)
+            .solution()
+            .unwrap();
+        let mut backtracking_events = Vec::new();
+        BacktrackingSolver::new().trace(sudoku.clone(), &mut backtracking_events);
+
+        for events in [events, backtracking_events] {
+            let n_events = events.len();
+            let mut path = SolutionPath::new(sudoku.clone(), events);
+            assert!(path.current().is_none());
+            assert!(!path.back());
+
+            path.go_to(usize::MAX);
+            assert_eq!(path.step(), n_events);
+            assert_eq!(path.current(), Some(&TraceEvent::Solution));
+            assert_eq!(path.board().serialize(), solution.serialize());
+            assert!(!path.forward());
+
+            // Stepping back and forth ends up on the same board
+            path.go_to(n_events / 2);
+            let board = path.board().serialize();
+            assert!(path.back());
+            assert!(path.forward());
+            assert_eq!(path.board().serialize(), board);
+
+            path.go_to(0);
+            assert_eq!(path.board().serialize(), PUZZLE);
+        }
+        Ok(())
+    }
+}
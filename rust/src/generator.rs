@@ -0,0 +1,167 @@
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{
+    algorithm_x_solver::AlgorithmXSudokuSolver,
+    sudoku::{BoardSize, Sudoku},
+    sudoku_solver::{SudokuSolver, Uniqueness},
+};
+
+/// Generates random sudokus with a unique solution.
+///
+/// All randomness is drawn from the given RNG, so a seeded RNG always produces the same
+/// sequence of sudokus.
+pub struct Generator<R: Rng> {
+    rng: R,
+    size: BoardSize,
+    solver: AlgorithmXSudokuSolver,
+}
+
+impl<R: Rng> Generator<R> {
+    pub fn new(rng: R) -> Self {
+        Self::with_size(rng, BoardSize::CLASSIC)
+    }
+
+    pub fn with_size(rng: R, size: BoardSize) -> Self {
+        Self {
+            rng,
+            size,
+            solver: AlgorithmXSudokuSolver::for_size(size),
+        }
+    }
+
+    /// Generates a random, completely filled sudoku.
+    pub fn solution(&mut self) -> Sudoku {
+        // The boxes on the diagonal don't constrain each other, so they can be filled with
+        // random permutations, the solver then completes the rest of the board. On 9x9 boards
+        // this always works, on some smaller boards the permutations may not fit together, so
+        // they are redrawn in that case.
+        let (box_rows, box_cols) = (self.size.box_rows(), self.size.box_cols());
+        let mut digits: Vec<u8> = (1..=self.size.side() as u8).collect();
+        loop {
+            let mut board = Sudoku::with_size(self.size);
+            for box_idx in 0..box_rows.min(box_cols) {
+                digits.shuffle(&mut self.rng);
+                for (i, &digit) in digits.iter().enumerate() {
+                    let row = box_rows * box_idx + i / box_cols;
+                    let col = box_cols * box_idx + i % box_cols;
+                    board.set(row, col, digit);
+                }
+            }
+
+            if let Some(solution) = self.solver.solve(board) {
+                return solution;
+            }
+        }
+    }
+
+    /// Generates a minimal puzzle, i.e. no clue can be removed without losing uniqueness.
+    pub fn puzzle(&mut self) -> Sudoku {
+        self.puzzle_with_clues(0)
+    }
+
+    /// Generates a puzzle by removing clues from a random solution until only `min_clues` clues
+    /// are left or no further clue can be removed without losing uniqueness.
+    pub fn puzzle_with_clues(&mut self, min_clues: usize) -> Sudoku {
+        let solution = self.solution();
+        self.reduce(solution, min_clues)
+    }
+
+    /// Removes clues in random order from a uniquely solvable board, as long as the solution
+    /// stays unique and more than `min_clues` clues are left.
+    pub fn reduce(&mut self, mut board: Sudoku, min_clues: usize) -> Sudoku {
+        let side = board.size().side();
+        let mut cells: Vec<usize> = (0..board.size().n_cells())
+            .filter(|&idx| board.get(idx / side, idx % side) != 0)
+            .collect();
+        cells.shuffle(&mut self.rng);
+
+        // Removing clues never makes a puzzle more constrained, so a clue that can't be removed
+        // now can't be removed later either. A single pass is therefore enough to get a minimal
+        // puzzle.
+        let mut n_clues = cells.len();
+        for idx in cells {
+            if n_clues <= min_clues {
+                break;
+            }
+
+            let (row, col) = (idx / side, idx % side);
+            let clue = board.get(row, col);
+            board.set(row, col, 0);
+            if self.solver.is_unique(&board) == Uniqueness::Unique {
+                n_clues -= 1;
+            } else {
+                board.set(row, col, clue);
+            }
+        }
+
+        board
+    }
+
+    /// Checks that a puzzle is uniquely solvable and that none of its clues can be removed.
+    pub fn is_minimal(&self, puzzle: &Sudoku) -> bool {
+        if self.solver.is_unique(puzzle) != Uniqueness::Unique {
+            return false;
+        }
+
+        let side = puzzle.size().side();
+        let mut board = puzzle.clone();
+        (0..puzzle.size().n_cells()).all(|idx| {
+            let (row, col) = (idx / side, idx % side);
+            let clue = board.get(row, col);
+            if clue == 0 {
+                return true;
+            }
+
+            board.set(row, col, 0);
+            let is_needed = self.solver.is_unique(&board) == Uniqueness::Multiple;
+            board.set(row, col, clue);
+            is_needed
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn test_solution() {
+        let mut generator = Generator::new(StdRng::seed_from_u64(42));
+        let first = generator.solution();
+        let second = generator.solution();
+        assert!(first.is_solved());
+        assert!(second.is_solved());
+        assert_ne!(first.serialize(), second.serialize());
+    }
+
+    #[test]
+    fn test_minimal_puzzle() {
+        let mut generator = Generator::new(StdRng::seed_from_u64(7));
+        let puzzle = generator.puzzle();
+        assert!(generator.is_minimal(&puzzle));
+
+        // The same seed produces the same puzzle
+        let mut generator = Generator::new(StdRng::seed_from_u64(7));
+        assert_eq!(generator.puzzle().serialize(), puzzle.serialize());
+    }
+
+    #[test]
+    fn test_other_sizes() {
+        for size in ["2x2", "2x3", "3x4"] {
+            let size: BoardSize = size.parse().unwrap();
+            let mut generator = Generator::with_size(StdRng::seed_from_u64(11), size);
+            let puzzle = generator.puzzle();
+            assert_eq!(puzzle.size(), size);
+            assert!(generator.is_minimal(&puzzle));
+        }
+    }
+
+    #[test]
+    fn test_puzzle_with_clues() {
+        let mut generator = Generator::new(StdRng::seed_from_u64(3));
+        let puzzle = generator.puzzle_with_clues(40);
+        assert_eq!(puzzle.iter().filter(|&&cell| cell != 0).count(), 40);
+        assert_eq!(generator.solver.is_unique(&puzzle), Uniqueness::Unique);
+    }
+}
@@ -0,0 +1,232 @@
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+use crate::{
+    algorithm_x::SearchMetrics,
+    algorithm_x_solver::AlgorithmXSudokuSolver,
+    error::{Result, SudokuError},
+    logical_solver::{LogicalSolver, Outcome, Technique},
+    sudoku::Sudoku,
+};
+
+/// Coarse difficulty classes, derived from the rating of a puzzle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Level {
+    /// Only singles are needed
+    Easy,
+    /// Locked candidates are needed
+    Medium,
+    /// Subsets or basic fish are needed
+    Hard,
+    /// Wings or coloring are needed
+    Expert,
+    /// The known techniques are not enough, the puzzle needs guessing
+    Extreme,
+}
+
+impl Level {
+    pub const ALL: [Level; 5] = [
+        Level::Easy,
+        Level::Medium,
+        Level::Hard,
+        Level::Expert,
+        Level::Extreme,
+    ];
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Level::Easy => write!(f, "easy"),
+            Level::Medium => write!(f, "medium"),
+            Level::Hard => write!(f, "hard"),
+            Level::Expert => write!(f, "expert"),
+            Level::Extreme => write!(f, "extreme"),
+        }
+    }
+}
+
+impl FromStr for Level {
+    type Err = SudokuError;
+
+    /// Parses a level by its name, like `easy` or `expert`.
+    fn from_str(s: &str) -> Result<Level> {
+        SudokuError::parse_name(s, "level", &Level::ALL)
+    }
+}
+
+/// The difficulty of a puzzle as judged by a human style solver.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Difficulty {
+    /// A rating in the spirit of the Sudoku Explainer scale. The rating of the hardest technique
+    /// is the base, e.g. 2.3 or 3.6, and every step beyond singles adds 0.01 to it, at most
+    /// 0.09. Puzzles logic can't solve are rated from 6.0 upwards by the size of the search.
+    pub rating: f32,
+    pub level: Level,
+    /// The hardest technique that was needed, if any
+    pub hardest: Option<Technique>,
+    /// How often every technique was applied
+    pub technique_counts: BTreeMap<Technique, usize>,
+    /// The size of the DLX search tree for the part logic couldn't solve, if logic stalled
+    pub search: Option<SearchMetrics>,
+}
+
+/// Rates a puzzle, see [`Grader::grade`].
+///
+/// Prefer a [`Grader`] when rating many puzzles, so the solvers are only set up once.
+pub fn grade(sudoku: &Sudoku) -> Option<Difficulty> {
+    Grader::new().grade(sudoku)
+}
+
+/// Rates puzzles by the techniques needed to solve them.
+pub struct Grader {
+    logical_solver: LogicalSolver,
+    search_solver: AlgorithmXSudokuSolver,
+}
+
+impl Grader {
+    pub fn new() -> Self {
+        Self {
+            logical_solver: LogicalSolver::new(),
+            search_solver: AlgorithmXSudokuSolver::new(),
+        }
+    }
+
+    /// The rating of a single application of a technique, roughly following Sudoku Explainer.
+    pub fn technique_rating(technique: Technique) -> f32 {
+        match technique {
+            Technique::HiddenSingle => 1.5,
+            Technique::NakedSingle => 2.3,
+            Technique::LockedCandidates => 2.6,
+            Technique::NakedPair => 3.0,
+            Technique::XWing => 3.2,
+            Technique::HiddenPair => 3.4,
+            Technique::NakedTriple => 3.6,
+            Technique::Swordfish => 3.8,
+            Technique::HiddenTriple => 4.0,
+            Technique::XYWing => 4.2,
+            Technique::SimpleColoring => 4.5,
+        }
+    }
+
+    /// Rates a puzzle by the techniques needed to solve it, or `None` if it has no solution.
+    pub fn grade(&self, sudoku: &Sudoku) -> Option<Difficulty> {
+        let solution = self.logical_solver.solve_with_steps(sudoku.clone());
+
+        let mut technique_counts = BTreeMap::new();
+        for step in &solution.steps {
+            *technique_counts.entry(step.technique).or_insert(0) += 1;
+        }
+
+        let hardest = technique_counts
+            .keys()
+            .copied()
+            .max_by(|a, b| Self::technique_rating(*a).total_cmp(&Self::technique_rating(*b)));
+        let hardest_rating = hardest.map_or(1.0, Self::technique_rating);
+
+        // Every step beyond singles adds a little, but never enough to reach the next technique
+        let n_advanced_steps = solution
+            .steps
+            .iter()
+            .filter(|step| step.technique >= Technique::LockedCandidates)
+            .count();
+        let usage_bonus = (n_advanced_steps as f32 * 0.01).min(0.09);
+
+        let (level, rating, search) = match solution.outcome {
+            Outcome::Solved => {
+                let level = match hardest_rating {
+                    r if r <= 2.3 => Level::Easy,
+                    r if r <= 2.6 => Level::Medium,
+                    r if r <= 4.0 => Level::Hard,
+                    _ => Level::Expert,
+                };
+                (level, hardest_rating + usage_bonus, None)
+            }
+            Outcome::Stuck | Outcome::Invalid => {
+                // Continue with a search from where logic stalled, a bigger search tree means a
+                // harder puzzle
+                let (n_solutions, metrics) = self
+                    .search_solver
+                    .count_solutions_with_metrics(&solution.board, 2);
+                if n_solutions == 0 {
+                    return None;
+                }
+                let rating = 6.0 + (metrics.nodes.max(1) as f32).log10();
+                (Level::Extreme, rating, Some(metrics))
+            }
+        };
+
+        Some(Difficulty {
+            rating,
+            level,
+            hardest,
+            technique_counts,
+            search,
+        })
+    }
+}
+
+impl Default for Grader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grade_str(puzzle: &str) -> Difficulty {
+        grade(&Sudoku::try_from_str(puzzle).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_levels() {
+        let easy = grade_str(
+            "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79",
+        );
+        assert_eq!(easy.level, Level::Easy);
+        assert_eq!(easy.hardest, Some(Technique::HiddenSingle));
+        assert_eq!(easy.rating, 1.5);
+        assert_eq!(easy.search, None);
+
+        // The solver tries the techniques cheapest first, so the hardest one is really needed
+        let ratings = Technique::ALL.map(Grader::technique_rating);
+        assert!(ratings.windows(2).all(|pair| pair[0] < pair[1]));
+
+        let hard = grade_str(
+            "....7.9...2.6.....5.....3...4.....217...3................2.1.8.9.....7.....4.....",
+        );
+        assert_eq!(hard.level, Level::Hard);
+        assert!(hard.technique_counts[&Technique::XWing] > 0);
+        assert!(hard.rating > 3.2 && hard.rating < 4.0);
+
+        let expert = grade_str(
+            "..9.....3.....9...7.....5.6..65..4.....3......28......3..75.6..6...........12.3.8",
+        );
+        assert_eq!(expert.level, Level::Expert);
+        assert!(expert.rating > hard.rating);
+
+        let extreme = grade_str(
+            "48.3............71.2.......7.5....6....2..8.............1.76...3.....4......5....",
+        );
+        assert_eq!(extreme.level, Level::Extreme);
+        assert!(extreme.search.unwrap().nodes > 0);
+        assert!(extreme.rating > expert.rating);
+    }
+
+    #[test]
+    fn test_invalid() {
+        let invalid = Sudoku::try_from_str(
+            "11...............................................................................",
+        )
+        .unwrap();
+        assert_eq!(grade(&invalid), None);
+
+        // The givens don't contradict each other, but r1c8 and r1c9 both need a 9
+        let unsolvable = Sudoku::try_from_str(
+            "1234567...........................8...........................8..................",
+        )
+        .unwrap();
+        assert_eq!(grade(&unsolvable), None);
+    }
+}
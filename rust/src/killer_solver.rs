@@ -0,0 +1,192 @@
+use crate::{
+    backtracking_solver::{BacktrackingSolver, Rule},
+    killer_sudoku::KillerSudoku,
+};
+
+// The cages of a killer sudoku as cell indices, checked on top of the units of the board
+struct CageSums {
+    all_digits: u32,
+    cages: Vec<(u32, Vec<usize>)>,
+}
+
+fn bit(digit: u8) -> u32 {
+    1 << (digit - 1)
+}
+
+/// Solves killer sudokus with the [`BacktrackingSolver`].
+///
+/// The cages are added to the peers of their cells, so a digit can't repeat within a cage. On
+/// top of that a digit is only a candidate if the rest of its cage can still reach the cage sum
+/// with the unused digits, which prunes most of the search tree early on.
+pub struct KillerSolver {}
+
+impl KillerSolver {
+    /// Returns the solved puzzle, or `None` if it has no solution.
+    pub fn solve(&self, puzzle: &KillerSudoku) -> Option<KillerSudoku> {
+        let mut solution = None;
+        Self::search(puzzle, 1, |cells| solution = Some(cells.to_vec()));
+
+        let cells = solution?;
+        let side = puzzle.size().side();
+        let mut puzzle = puzzle.clone();
+        for (idx, digit) in cells.into_iter().enumerate() {
+            puzzle.set(idx / side, idx % side, digit);
+        }
+        Some(puzzle)
+    }
+
+    /// Counts the solutions of the puzzle, but stops as soon as `limit` solutions have been
+    /// found.
+    pub fn count_solutions(&self, puzzle: &KillerSudoku, limit: usize) -> usize {
+        Self::search(puzzle, limit, |_| {})
+    }
+
+    // Runs the search until `limit` solutions have been found and returns their number
+    fn search(puzzle: &KillerSudoku, limit: usize, on_solution: impl FnMut(&[u8])) -> usize {
+        let side = puzzle.size().side();
+        let cages: Vec<(u32, Vec<usize>)> = puzzle
+            .cages()
+            .iter()
+            .map(|cage| {
+                let cells = cage.cells.iter().map(|&(row, col)| row * side + col);
+                (cage.sum, cells.collect())
+            })
+            .collect();
+        let groups: Vec<Vec<usize>> = cages.iter().map(|(_, cells)| cells.clone()).collect();
+
+        let sudoku = puzzle.sudoku();
+        let solver = BacktrackingSolver::with_units(sudoku.units().with_groups(&groups));
+        let rule = CageSums {
+            all_digits: u32::MAX >> (u32::BITS as usize - side),
+            cages,
+        };
+        solver.search_with_rule(sudoku, limit, &rule, on_solution)
+    }
+}
+
+impl CageSums {
+    // Checks whether `n_cells` distinct digits from `available` can add up to `sum`. Only the
+    // smallest and largest possible sums are compared, which is exact for a single cell.
+    fn is_reachable(sum: u32, n_cells: usize, available: u32) -> bool {
+        match n_cells {
+            0 => return sum == 0,
+            1 => return (1..=u32::BITS).contains(&sum) && available & (1 << (sum - 1)) != 0,
+            _ if available.count_ones() < n_cells as u32 => return false,
+            _ => {}
+        }
+
+        let digit_sum = |mask: u32| -> u32 {
+            (0..u32::BITS)
+                .filter(|i| mask & (1 << i) != 0)
+                .map(|i| i + 1)
+                .sum()
+        };
+        // Drop the largest digits to get the smallest sum and the smallest for the largest one
+        let mut smallest = available;
+        while smallest.count_ones() > n_cells as u32 {
+            smallest &= !(1 << (u32::BITS - 1 - smallest.leading_zeros()));
+        }
+        let mut largest = available;
+        while largest.count_ones() > n_cells as u32 {
+            largest &= largest - 1;
+        }
+        (digit_sum(smallest)..=digit_sum(largest)).contains(&sum)
+    }
+}
+
+impl Rule for CageSums {
+    fn prune(&self, cells: &[u8], candidates: &mut [u32]) -> Option<bool> {
+        let mut changed = false;
+        for (sum, cage) in &self.cages {
+            // The part of the sum that still has to be filled in, the number of empty cells
+            // and the digits already used in the cage
+            let (mut placed, mut n_empty, mut used) = (0, 0, 0);
+            for &idx in cage {
+                match cells[idx] {
+                    0 => n_empty += 1,
+                    digit => {
+                        placed += digit as u32;
+                        used |= bit(digit);
+                    }
+                }
+            }
+            let remaining = sum.checked_sub(placed)?;
+            if n_empty == 0 {
+                if remaining != 0 {
+                    return None;
+                }
+                continue;
+            }
+
+            for &idx in cage.iter().filter(|&&idx| cells[idx] == 0) {
+                let mut fitting = 0;
+                let mut remaining_candidates = candidates[idx];
+                while remaining_candidates != 0 {
+                    let digit = remaining_candidates.trailing_zeros() as u8 + 1;
+                    remaining_candidates &= remaining_candidates - 1;
+
+                    let fits = remaining >= digit as u32
+                        && Self::is_reachable(
+                            remaining - digit as u32,
+                            n_empty - 1,
+                            self.all_digits & !used & !bit(digit),
+                        );
+                    if fits {
+                        fitting |= bit(digit);
+                    }
+                }
+                if fitting != candidates[idx] {
+                    candidates[idx] = fitting;
+                    changed = true;
+                }
+            }
+        }
+        Some(changed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solve() {
+        let puzzle =
+            KillerSudoku::try_from_str(include_str!("../resources/killer_sudoku.txt")).unwrap();
+        let solver = KillerSolver {};
+        let solution = solver.solve(&puzzle).unwrap();
+        assert!(solution.is_solved());
+        assert_eq!(
+            solution.sudoku().serialize(),
+            "417369825632158947958724316825437169791586432346912758289643571573291684164875293"
+        );
+        assert_eq!(solver.count_solutions(&puzzle, 2), 1);
+
+        // Givens which contradict a cage make the puzzle unsolvable
+        let mut invalid = puzzle.clone();
+        invalid.set(8, 2, 5);
+        assert!(solver.solve(&invalid).is_none());
+    }
+
+    #[test]
+    fn test_multiple_solutions() {
+        let puzzle = KillerSudoku::try_from_str(
+            "
+            3 = r1c1 r1c2
+            7 = r1c3 r1c4
+            7 = r2c1 r2c2
+            3 = r2c3 r2c4
+            6 = r3c1 r4c1
+            4 = r3c2 r4c2
+            5 = r3c3 r4c3
+            5 = r3c4 r4c4
+            ",
+        )
+        .unwrap();
+
+        let solver = KillerSolver {};
+        assert!(solver.solve(&puzzle).unwrap().is_solved());
+        assert_eq!(solver.count_solutions(&puzzle, 10), 4);
+        assert_eq!(solver.count_solutions(&puzzle, 3), 3);
+    }
+}
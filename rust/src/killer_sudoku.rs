@@ -0,0 +1,338 @@
+use std::{collections::HashSet, fmt, str::FromStr};
+
+use crate::{
+    error::{Result, SudokuError},
+    sudoku::{BoardSize, Sudoku},
+};
+
+/// A group of cells whose digits add up to `sum`, without repeating a digit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cage {
+    pub sum: u32,
+    /// The cells of the cage as 0-based (row, col)
+    pub cells: Vec<(usize, usize)>,
+}
+
+impl Cage {
+    pub fn new(sum: u32, cells: Vec<(usize, usize)>) -> Cage {
+        Cage { sum, cells }
+    }
+
+    /// The smallest and largest sum `n_cells` distinct digits of a board with `side` digits
+    /// can add up to.
+    pub fn sum_range(n_cells: usize, side: usize) -> (u32, u32) {
+        let (n, side) = (n_cells as u32, side as u32);
+        let min = n * (n + 1) / 2;
+        let max = n * (2 * side - n + 1) / 2;
+        (min, max)
+    }
+}
+
+impl fmt::Display for Cage {
+    /// Formats the cage as `sum = r1c1 r1c2 ...` with 1-based cell coordinates.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} =", self.sum)?;
+        for (row, col) in &self.cells {
+            write!(f, " r{}c{}", row + 1, col + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Cage {
+    type Err = SudokuError;
+
+    /// Parses a cage like `15 = r1c1 r1c2 r2c1`.
+    fn from_str(s: &str) -> Result<Cage> {
+        let invalid = |reason: String| SudokuError::InvalidCage {
+            cage: s.to_string(),
+            reason,
+        };
+        let Some((sum, cells)) = s.split_once('=') else {
+            return Err(invalid(
+                "expected something like 15 = r1c1 r1c2".to_string(),
+            ));
+        };
+        let sum = sum
+            .trim()
+            .parse()
+            .map_err(|_| invalid(format!("invalid sum '{}'", sum.trim())))?;
+        let cells = cells
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|cell| !cell.is_empty())
+            .map(parse_cell)
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Cage { sum, cells })
+    }
+}
+
+// Parses a 1-based cell like `r3c12` into a 0-based (row, col)
+fn parse_cell(cell: &str) -> Result<(usize, usize)> {
+    let lower = cell.to_ascii_lowercase();
+    let parsed = lower
+        .strip_prefix('r')
+        .and_then(|rest| rest.split_once('c'))
+        .and_then(|(row, col)| Some((row.parse::<usize>().ok()?, col.parse::<usize>().ok()?)));
+    match parsed {
+        Some((row, col)) if row > 0 && col > 0 => Ok((row - 1, col - 1)),
+        _ => Err(SudokuError::InvalidCell(cell.to_string())),
+    }
+}
+
+/// A sudoku whose cells are additionally grouped into cages with a given sum.
+///
+/// The text format has one cage per line, written as `sum = r1c1 r1c2 ...`. Lines starting
+/// with `#` are comments. Optionally the board size can be given by a line like `size 2x3` and
+/// givens by a line in the usual single line sudoku format, otherwise the size is derived from
+/// the cells of the cages.
+#[derive(Debug, Clone)]
+pub struct KillerSudoku {
+    sudoku: Sudoku,
+    cages: Vec<Cage>,
+}
+
+impl KillerSudoku {
+    /// Creates a killer sudoku from a board with givens and the cages. Every cell may belong to
+    /// at most one cage, cells outside of all cages are allowed.
+    pub fn new(sudoku: Sudoku, cages: Vec<Cage>) -> Result<KillerSudoku> {
+        let invalid = |cage: &Cage, reason: String| SudokuError::InvalidCage {
+            cage: cage.to_string(),
+            reason,
+        };
+        let side = sudoku.size().side();
+        let mut caged = HashSet::new();
+        for cage in &cages {
+            if cage.cells.is_empty() || cage.cells.len() > side {
+                return Err(invalid(cage, format!("a cage needs 1 to {} cells", side)));
+            }
+            let (min, max) = Cage::sum_range(cage.cells.len(), side);
+            if !(min..=max).contains(&cage.sum) {
+                let reason = format!(
+                    "the sum of {} cells must be between {} and {}",
+                    cage.cells.len(),
+                    min,
+                    max
+                );
+                return Err(invalid(cage, reason));
+            }
+            for &(row, col) in &cage.cells {
+                if row >= side || col >= side {
+                    let reason = format!("r{}c{} is outside of the board", row + 1, col + 1);
+                    return Err(invalid(cage, reason));
+                }
+                if !caged.insert((row, col)) {
+                    let reason = format!("r{}c{} is already part of a cage", row + 1, col + 1);
+                    return Err(invalid(cage, reason));
+                }
+            }
+        }
+        Ok(KillerSudoku { sudoku, cages })
+    }
+
+    pub fn try_from_str(s: &str) -> Result<KillerSudoku> {
+        let mut size = None;
+        let mut givens = None;
+        let mut cages = Vec::new();
+        for line in s.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(board_size) = line.strip_prefix("size") {
+                size = Some(board_size.trim().parse::<BoardSize>()?);
+            } else if line.contains('=') {
+                cages.push(line.parse::<Cage>()?);
+            } else if givens.is_none() {
+                givens = Some(line);
+            } else {
+                return Err(SudokuError::InvalidKillerSudoku(
+                    "more than one line of givens".to_string(),
+                ));
+            }
+        }
+
+        let size = match (size, givens) {
+            (Some(size), _) => size,
+            (None, Some(givens)) => Sudoku::try_from_str(givens)?.size(),
+            (None, None) => {
+                let side = cages
+                    .iter()
+                    .flat_map(|cage| &cage.cells)
+                    .map(|&(row, col)| row.max(col) + 1)
+                    .max()
+                    .unwrap_or(0);
+                BoardSize::from_n_cells(side * side).ok_or_else(|| {
+                    SudokuError::InvalidKillerSudoku(format!(
+                        "can't derive the board size from cages spanning {} rows",
+                        side
+                    ))
+                })?
+            }
+        };
+
+        let sudoku = match givens {
+            Some(givens) => Sudoku::try_from_str_with_size(givens, size)?,
+            None => Sudoku::with_size(size),
+        };
+        KillerSudoku::new(sudoku, cages)
+    }
+
+    /// The board with the givens, or with the solution once it is solved
+    pub fn sudoku(&self) -> &Sudoku {
+        &self.sudoku
+    }
+
+    pub fn cages(&self) -> &[Cage] {
+        &self.cages
+    }
+
+    pub fn size(&self) -> BoardSize {
+        self.sudoku.size()
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> u8 {
+        self.sudoku.get(row, col)
+    }
+
+    pub fn set(&mut self, row: usize, col: usize, val: u8) {
+        self.sudoku.set(row, col, val)
+    }
+
+    pub fn cage_containing(&self, row: usize, col: usize) -> Option<&Cage> {
+        self.cages
+            .iter()
+            .find(|cage| cage.cells.contains(&(row, col)))
+    }
+
+    pub fn is_valid(&self, row: usize, col: usize, val: u8) -> bool {
+        if !self.sudoku.is_valid(row, col, val) {
+            return false;
+        }
+
+        let Some(cage) = self.cage_containing(row, col) else {
+            return true;
+        };
+
+        // Check cage constraint, the digit may not repeat and the sum may not be exceeded
+        let others = cage
+            .cells
+            .iter()
+            .filter(|&&cell| cell != (row, col))
+            .map(|&(r, c)| self.get(r, c));
+        let mut sum = val as u32;
+        let mut n_empty = 0;
+        for other in others {
+            if other == val {
+                return false;
+            }
+            sum += other as u32;
+            n_empty += usize::from(other == 0);
+        }
+        if n_empty == 0 {
+            sum == cage.sum
+        } else {
+            sum < cage.sum
+        }
+    }
+
+    pub fn is_solved(&self) -> bool {
+        if !self.sudoku.is_solved() {
+            return false;
+        }
+
+        // Check cage constraints
+        self.cages.iter().all(|cage| {
+            let digits = cage
+                .cells
+                .iter()
+                .map(|&(row, col)| self.get(row, col))
+                .collect::<HashSet<_>>();
+            let sum = digits.iter().map(|&digit| digit as u32).sum::<u32>();
+            digits.len() == cage.cells.len() && sum == cage.sum
+        })
+    }
+}
+
+impl TryFrom<&str> for KillerSudoku {
+    type Error = SudokuError;
+
+    fn try_from(s: &str) -> Result<KillerSudoku> {
+        KillerSudoku::try_from_str(s)
+    }
+}
+
+impl fmt::Display for KillerSudoku {
+    /// Formats the killer sudoku in the text format it is parsed from.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "size {}", self.size())?;
+        if self.sudoku.iter().any(|&cell| cell != 0) {
+            writeln!(f, "{}", self.sudoku.serialize())?;
+        }
+        for cage in &self.cages {
+            writeln!(f, "{}", cage)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUZZLE: &str = "
+        # A 4x4 killer sudoku
+        3 = r1c1 r1c2
+        7 = r1c3 r1c4
+        7 = r2c1 r2c2
+        3 = r2c3 r2c4
+        6 = r3c1 r4c1
+        4 = r3c2 r4c2
+        5 = r3c3 r4c3
+        5 = r3c4 r4c4
+    ";
+
+    #[test]
+    fn test_parse() -> Result<()> {
+        let killer = KillerSudoku::try_from_str(PUZZLE)?;
+        assert_eq!(killer.size(), BoardSize::new(2, 2)?);
+        assert_eq!(killer.cages().len(), 8);
+        assert_eq!(killer.cages()[1], Cage::new(7, vec![(0, 2), (0, 3)]));
+        assert_eq!(killer.cage_containing(3, 1).unwrap().sum, 4);
+
+        // Formatting and parsing again gives the same puzzle
+        let reparsed = KillerSudoku::try_from_str(&killer.to_string())?;
+        assert_eq!(reparsed.cages(), killer.cages());
+        assert_eq!(reparsed.size(), killer.size());
+
+        let with_givens = KillerSudoku::try_from_str("1...............\n3 = r1c1 r1c2")?;
+        assert_eq!(with_givens.get(0, 0), 1);
+
+        assert!(KillerSudoku::try_from_str("3 = r1c1 r1").is_err());
+        assert!(KillerSudoku::try_from_str("size 2x2\n3 = r1c1 r5c1").is_err());
+        assert!(KillerSudoku::try_from_str("size 2x2\n3 = r1c1 r1c2\n4 = r1c2 r2c2").is_err());
+        assert!(KillerSudoku::try_from_str("size 2x2\n2 = r1c1 r1c2").is_err());
+        assert!(KillerSudoku::try_from_str("size 2x2\n12 = r1c1 r1c2 r1c3 r1c4 r2c1").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_solved() -> Result<()> {
+        let mut killer = KillerSudoku::try_from_str(PUZZLE)?;
+        assert!(!killer.is_solved());
+        assert!(killer.is_valid(0, 0, 1));
+        assert!(!killer.is_valid(0, 0, 3));
+
+        for (i, digit) in "1234341221434321".chars().enumerate() {
+            killer.set(i / 4, i % 4, digit.to_digit(10).unwrap() as u8);
+        }
+        assert!(killer.sudoku().is_solved());
+        assert!(!killer.is_solved());
+
+        for (i, digit) in "1243341221344321".chars().enumerate() {
+            killer.set(i / 4, i % 4, digit.to_digit(10).unwrap() as u8);
+        }
+        assert!(killer.is_solved());
+
+        Ok(())
+    }
+}
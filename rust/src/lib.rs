@@ -1,8 +1,20 @@
-mod algorithm_x;
+pub mod algorithm_x;
 pub mod algorithm_x_solver;
 pub mod backtracking_solver;
+pub mod format;
+pub mod game;
+pub mod generator;
+pub mod grading;
+pub mod killer_solver;
+pub mod killer_sudoku;
+pub mod logical_solver;
+pub mod puzzle_file;
+pub mod regions;
 
+mod error;
 pub mod sudoku;
 mod sudoku_solver;
+mod units;
 
-pub use sudoku_solver::SudokuSolver;
+pub use error::SudokuError;
+pub use sudoku_solver::{SolveOutcome, SudokuSolver, TraceEvent, Uniqueness};
@@ -0,0 +1,935 @@
+use std::{fmt, sync::Arc};
+
+use crate::{
+    algorithm_x::SearchMetrics,
+    algorithm_x_solver::AlgorithmXSudokuSolver,
+    regions::Regions,
+    sudoku::{BoardSize, Sudoku, Variant},
+    sudoku_solver::SudokuSolver,
+    units::Units,
+};
+
+/// The techniques known to the [`LogicalSolver`], ordered from easiest to hardest like their
+/// ratings in the [`Grader`](crate::grading::Grader). The solver tries them in this order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Technique {
+    HiddenSingle,
+    NakedSingle,
+    LockedCandidates,
+    NakedPair,
+    XWing,
+    HiddenPair,
+    NakedTriple,
+    Swordfish,
+    HiddenTriple,
+    XYWing,
+    SimpleColoring,
+}
+
+impl Technique {
+    pub const ALL: [Technique; 11] = [
+        Technique::HiddenSingle,
+        Technique::NakedSingle,
+        Technique::LockedCandidates,
+        Technique::NakedPair,
+        Technique::XWing,
+        Technique::HiddenPair,
+        Technique::NakedTriple,
+        Technique::Swordfish,
+        Technique::HiddenTriple,
+        Technique::XYWing,
+        Technique::SimpleColoring,
+    ];
+}
+
+impl fmt::Display for Technique {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Technique::NakedSingle => "Naked Single",
+            Technique::HiddenSingle => "Hidden Single",
+            Technique::LockedCandidates => "Locked Candidates",
+            Technique::NakedPair => "Naked Pair",
+            Technique::HiddenPair => "Hidden Pair",
+            Technique::NakedTriple => "Naked Triple",
+            Technique::HiddenTriple => "Hidden Triple",
+            Technique::XWing => "X-Wing",
+            Technique::Swordfish => "Swordfish",
+            Technique::XYWing => "XY-Wing",
+            Technique::SimpleColoring => "Simple Coloring",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A single deduction made by the [`LogicalSolver`].
+///
+/// All coordinates are zero based `(row, col)` pairs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    pub technique: Technique,
+    /// The cells forming the pattern that allowed the deduction
+    pub cells: Vec<(usize, usize)>,
+    /// Digits placed by this step as `(row, col, digit)`
+    pub placements: Vec<(usize, usize, u8)>,
+    /// Candidates removed by this step as `(row, col, digit)`
+    pub eliminations: Vec<(usize, usize, u8)>,
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:", self.technique)?;
+        for &(row, col, digit) in &self.placements {
+            write!(f, " r{}c{}={}", row + 1, col + 1, digit)?;
+        }
+        for &(row, col, digit) in &self.eliminations {
+            write!(f, " r{}c{}<>{}", row + 1, col + 1, digit)?;
+        }
+        Ok(())
+    }
+}
+
+/// How far the [`LogicalSolver`] got with a puzzle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Solved,
+    /// None of the known techniques makes any further progress
+    Stuck,
+    /// The puzzle contains a contradiction, e.g. a cell without candidates
+    Invalid,
+}
+
+#[derive(Debug, Clone)]
+pub struct LogicalSolution {
+    /// The board after the last step
+    pub board: Sudoku,
+    pub steps: Vec<Step>,
+    pub outcome: Outcome,
+}
+
+// The working state of the solver: the placed digits and a candidate bitmask per cell, where
+// bit `d - 1` is set if the digit `d` is still possible.
+#[derive(Clone)]
+struct Grid {
+    cells: Vec<u8>,
+    candidates: Vec<u32>,
+}
+
+impl Grid {
+    fn candidates(&self, idx: usize) -> u32 {
+        if self.cells[idx] == 0 {
+            self.candidates[idx]
+        } else {
+            0
+        }
+    }
+
+    fn has_candidate(&self, idx: usize, digit: u8) -> bool {
+        self.candidates(idx) & bit(digit) != 0
+    }
+}
+
+#[inline(always)]
+fn bit(digit: u8) -> u32 {
+    1 << (digit - 1)
+}
+
+fn digits(mask: u32) -> impl Iterator<Item = u8> {
+    (1..=u32::BITS as u8).filter(move |&digit| mask & bit(digit) != 0)
+}
+
+// All combinations of `k` elements of `items`, in lexicographic order.
+fn combinations<T: Copy>(items: &[T], k: usize) -> Vec<Vec<T>> {
+    if k == 0 {
+        return vec![Vec::new()];
+    }
+    if items.len() < k {
+        return Vec::new();
+    }
+
+    let mut result = Vec::new();
+    for (i, &first) in items.iter().enumerate() {
+        for mut rest in combinations(&items[i + 1..], k - 1) {
+            rest.insert(0, first);
+            result.push(rest);
+        }
+    }
+    result
+}
+
+/// A solver which only uses the techniques a human would use and records every deduction.
+pub struct LogicalSolver {
+    // The units and peers of the boards the solver was set up for
+    units: Arc<Units>,
+}
+
+impl LogicalSolver {
+    pub fn new() -> Self {
+        Self::for_size(BoardSize::CLASSIC)
+    }
+
+    /// Creates a solver for boards of the given size. Boards of other sizes can still be
+    /// solved, with the units of the board instead.
+    pub fn for_size(size: BoardSize) -> Self {
+        Self::with_variants(size, &[])
+    }
+
+    /// Creates a solver for boards of the given size with additional variant rules. Like for
+    /// the size, boards with other variants are solved with their own units.
+    pub fn with_variants(size: BoardSize, variants: &[Variant]) -> Self {
+        Self::build(size, variants, None)
+    }
+
+    /// Creates a solver for boards with the same size, variants and regions as the given one.
+    pub fn for_board(board: &Sudoku) -> Self {
+        Self {
+            units: Arc::clone(board.units()),
+        }
+    }
+
+    fn build(size: BoardSize, variants: &[Variant], regions: Option<&Regions>) -> Self {
+        let mut variants = variants.to_vec();
+        variants.sort_unstable();
+        variants.dedup();
+        Self {
+            units: Units::get(size, &variants, regions),
+        }
+    }
+
+    /// Solves the board step by step, until it is solved or no technique applies anymore.
+    ///
+    /// The search starts from the candidates of the board, so eliminations already made on it
+    /// are kept, and the returned board carries the candidates left at the end.
+    pub fn solve_with_steps(&self, board: Sudoku) -> LogicalSolution {
+        if !self.fits(&board) {
+            return Self::for_board(&board).solve_with_steps(board);
+        }
+
+        let mut steps = Vec::new();
+        let Some(mut grid) = self.init_grid(&board) else {
+            return LogicalSolution {
+                board,
+                steps,
+                outcome: Outcome::Invalid,
+            };
+        };
+
+        let outcome = loop {
+            if grid.cells.iter().all(|&cell| cell != 0) {
+                break Outcome::Solved;
+            }
+            if self.is_broken(&grid) {
+                break Outcome::Invalid;
+            }
+            let Some(step) = self.find_step(&grid) else {
+                break Outcome::Stuck;
+            };
+            self.apply(&mut grid, &step);
+            steps.push(step);
+        };
+
+        let mut board = board;
+        for (idx, &cell) in grid.cells.iter().enumerate() {
+            let (row, col) = self.coords(idx);
+            board.set(row, col, cell);
+        }
+        // Hand the eliminations made so far back, in case the board couldn't be solved
+        for (idx, &candidates) in grid.candidates.iter().enumerate() {
+            let (row, col) = self.coords(idx);
+            board.set_candidates(row, col, candidates);
+        }
+
+        LogicalSolution {
+            board,
+            steps,
+            outcome,
+        }
+    }
+
+    /// Finds the easiest deduction that can be made on the given board, taking the
+    /// eliminations already made on it into account.
+    pub fn next_step(&self, board: &Sudoku) -> Option<Step> {
+        if !self.fits(board) {
+            return Self::for_board(board).next_step(board);
+        }
+
+        let grid = self.init_grid(board)?;
+        if self.is_broken(&grid) {
+            return None;
+        }
+        self.find_step(&grid)
+    }
+
+    // Checks whether the units were set up for boards like the given one
+    fn fits(&self, board: &Sudoku) -> bool {
+        self.units.same_layout(board.units())
+    }
+
+    fn side(&self) -> usize {
+        self.units.size().side()
+    }
+
+    fn n_cells(&self) -> usize {
+        self.units.size().n_cells()
+    }
+
+    // The mask with a bit set for every digit
+    fn all_digits(&self) -> u32 {
+        u32::MAX >> (u32::BITS as usize - self.side())
+    }
+
+    fn coords(&self, idx: usize) -> (usize, usize) {
+        (idx / self.side(), idx % self.side())
+    }
+
+    fn sees(&self, a: usize, b: usize) -> bool {
+        self.units.sees(a, b)
+    }
+
+    fn init_grid(&self, board: &Sudoku) -> Option<Grid> {
+        let mut grid = Grid {
+            cells: vec![0; self.n_cells()],
+            candidates: vec![self.all_digits(); self.n_cells()],
+        };
+        for (idx, &cell) in board.iter().enumerate() {
+            if cell == 0 {
+                continue;
+            }
+            if !(1..=self.side() as u8).contains(&cell) || !grid.has_candidate(idx, cell) {
+                return None;
+            }
+            self.place(&mut grid, idx, cell);
+        }
+        // Keep the eliminations already made on the board
+        for idx in (0..self.n_cells()).filter(|&idx| grid.cells[idx] == 0) {
+            let (row, col) = self.coords(idx);
+            grid.candidates[idx] &= board.candidates(row, col);
+        }
+        Some(grid)
+    }
+
+    fn place(&self, grid: &mut Grid, idx: usize, digit: u8) {
+        grid.cells[idx] = digit;
+        grid.candidates[idx] = 0;
+        for &peer in self.units.peers(idx) {
+            grid.candidates[peer] &= !bit(digit);
+        }
+    }
+
+    fn apply(&self, grid: &mut Grid, step: &Step) {
+        for &(row, col, digit) in &step.placements {
+            self.place(grid, row * self.side() + col, digit);
+        }
+        for &(row, col, digit) in &step.eliminations {
+            grid.candidates[row * self.side() + col] &= !bit(digit);
+        }
+    }
+
+    fn is_broken(&self, grid: &Grid) -> bool {
+        let empty_cell =
+            (0..self.n_cells()).any(|idx| grid.cells[idx] == 0 && grid.candidates[idx] == 0);
+        let missing_digit = self.units.units().iter().any(|unit| {
+            let placed = unit.iter().fold(0, |mask, &idx| match grid.cells[idx] {
+                0 => mask,
+                digit => mask | bit(digit),
+            });
+            let possible = unit
+                .iter()
+                .fold(0, |mask, &idx| mask | grid.candidates(idx));
+            placed | possible != self.all_digits()
+        });
+        empty_cell || missing_digit
+    }
+
+    fn find_step(&self, grid: &Grid) -> Option<Step> {
+        Technique::ALL
+            .iter()
+            .find_map(|&technique| match technique {
+                Technique::NakedSingle => self.naked_single(grid),
+                Technique::HiddenSingle => self.hidden_single(grid),
+                Technique::LockedCandidates => self.locked_candidates(grid),
+                Technique::NakedPair => self.naked_subset(grid, 2, technique),
+                Technique::HiddenPair => self.hidden_subset(grid, 2, technique),
+                Technique::NakedTriple => self.naked_subset(grid, 3, technique),
+                Technique::HiddenTriple => self.hidden_subset(grid, 3, technique),
+                Technique::XWing => self.fish(grid, 2, technique),
+                Technique::Swordfish => self.fish(grid, 3, technique),
+                Technique::XYWing => self.xy_wing(grid),
+                Technique::SimpleColoring => self.simple_coloring(grid),
+            })
+    }
+
+    fn step(
+        &self,
+        technique: Technique,
+        cells: impl IntoIterator<Item = usize>,
+        placements: Vec<(usize, usize, u8)>,
+        eliminations: impl IntoIterator<Item = (usize, u8)>,
+    ) -> Step {
+        Step {
+            technique,
+            cells: cells.into_iter().map(|idx| self.coords(idx)).collect(),
+            placements,
+            eliminations: eliminations
+                .into_iter()
+                .map(|(idx, digit)| {
+                    let (row, col) = self.coords(idx);
+                    (row, col, digit)
+                })
+                .collect(),
+        }
+    }
+
+    fn naked_single(&self, grid: &Grid) -> Option<Step> {
+        (0..self.n_cells()).find_map(|idx| {
+            let candidates = grid.candidates(idx);
+            if candidates.count_ones() != 1 {
+                return None;
+            }
+            let digit = candidates.trailing_zeros() as u8 + 1;
+            let (row, col) = self.coords(idx);
+            Some(self.step(Technique::NakedSingle, [idx], vec![(row, col, digit)], []))
+        })
+    }
+
+    fn hidden_single(&self, grid: &Grid) -> Option<Step> {
+        self.units.units().iter().find_map(|unit| {
+            (1..=self.side() as u8).find_map(|digit| {
+                let mut positions = unit.iter().filter(|&&idx| grid.has_candidate(idx, digit));
+                let idx = *positions.next()?;
+                if positions.next().is_some() {
+                    return None;
+                }
+                let (row, col) = self.coords(idx);
+                Some(self.step(
+                    Technique::HiddenSingle,
+                    unit.iter().copied(),
+                    vec![(row, col, digit)],
+                    [],
+                ))
+            })
+        })
+    }
+
+    fn locked_candidates(&self, grid: &Grid) -> Option<Step> {
+        for unit in self.units.units() {
+            for digit in 1..=self.side() as u8 {
+                let positions: Vec<usize> = unit
+                    .iter()
+                    .copied()
+                    .filter(|&idx| grid.has_candidate(idx, digit))
+                    .collect();
+                if positions.len() < 2 {
+                    continue;
+                }
+
+                // All other units which contain every position of the digit in this unit
+                let first = positions[0];
+                let others = self
+                    .units
+                    .cell_units(first)
+                    .iter()
+                    .map(|&unit_idx| &self.units.units()[unit_idx])
+                    .filter(|other| *other != unit)
+                    .filter(|other| positions.iter().all(|idx| other.contains(idx)));
+
+                for other in others {
+                    let eliminations: Vec<_> = other
+                        .iter()
+                        .copied()
+                        .filter(|idx| !unit.contains(idx) && grid.has_candidate(*idx, digit))
+                        .map(|idx| (idx, digit))
+                        .collect();
+                    if !eliminations.is_empty() {
+                        return Some(self.step(
+                            Technique::LockedCandidates,
+                            positions,
+                            Vec::new(),
+                            eliminations,
+                        ));
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    fn naked_subset(&self, grid: &Grid, size: usize, technique: Technique) -> Option<Step> {
+        for unit in self.units.units() {
+            let empties: Vec<usize> = unit
+                .iter()
+                .copied()
+                .filter(|&idx| (2..=size as u32).contains(&grid.candidates(idx).count_ones()))
+                .collect();
+
+            for subset in combinations(&empties, size) {
+                let union = subset
+                    .iter()
+                    .fold(0, |mask, &idx| mask | grid.candidates(idx));
+                if union.count_ones() as usize != size {
+                    continue;
+                }
+
+                let eliminations: Vec<_> = unit
+                    .iter()
+                    .copied()
+                    .filter(|idx| !subset.contains(idx))
+                    .flat_map(|idx| digits(grid.candidates(idx) & union).map(move |d| (idx, d)))
+                    .collect();
+                if !eliminations.is_empty() {
+                    return Some(self.step(technique, subset, Vec::new(), eliminations));
+                }
+            }
+        }
+        None
+    }
+
+    fn hidden_subset(&self, grid: &Grid, size: usize, technique: Technique) -> Option<Step> {
+        for unit in self.units.units() {
+            // For every digit the positions within the unit where it can still go
+            let positions = |digit: u8| {
+                (0..unit.len()).fold(0u32, |mask, i| match grid.has_candidate(unit[i], digit) {
+                    true => mask | 1 << i,
+                    false => mask,
+                })
+            };
+            let open_digits: Vec<u8> = (1..=self.side() as u8)
+                .filter(|&digit| (2..=size as u32).contains(&positions(digit).count_ones()))
+                .collect();
+
+            for subset in combinations(&open_digits, size) {
+                let union = subset
+                    .iter()
+                    .fold(0, |mask, &digit| mask | positions(digit));
+                if union.count_ones() as usize != size {
+                    continue;
+                }
+
+                let subset_mask = subset.iter().fold(0, |mask, &digit| mask | bit(digit));
+                let cells: Vec<usize> = (0..unit.len())
+                    .filter(|i| union & 1 << i != 0)
+                    .map(|i| unit[i])
+                    .collect();
+                let eliminations: Vec<_> = cells
+                    .iter()
+                    .flat_map(|&idx| {
+                        digits(grid.candidates(idx) & !subset_mask).map(move |d| (idx, d))
+                    })
+                    .collect();
+                if !eliminations.is_empty() {
+                    return Some(self.step(technique, cells, Vec::new(), eliminations));
+                }
+            }
+        }
+        None
+    }
+
+    fn fish(&self, grid: &Grid, size: usize, technique: Technique) -> Option<Step> {
+        for digit in 1..=self.side() as u8 {
+            // First with rows as base and columns as cover sets, then the other way around
+            let side = self.side();
+            for (base_units, cover_units) in [(0..side, side..2 * side), (side..2 * side, 0..side)]
+            {
+                let base_units = &self.units.units()[base_units];
+                let cover_units = &self.units.units()[cover_units];
+
+                let cover_mask = |unit: &[usize]| {
+                    (0..unit.len()).fold(0u32, |mask, i| match grid.has_candidate(unit[i], digit) {
+                        true => mask | 1 << i,
+                        false => mask,
+                    })
+                };
+                let bases: Vec<usize> = (0..side)
+                    .filter(|&i| {
+                        (2..=size as u32).contains(&cover_mask(&base_units[i]).count_ones())
+                    })
+                    .collect();
+
+                for subset in combinations(&bases, size) {
+                    let union = subset
+                        .iter()
+                        .fold(0, |mask, &i| mask | cover_mask(&base_units[i]));
+                    if union.count_ones() as usize != size {
+                        continue;
+                    }
+
+                    let base_cells: Vec<usize> = subset
+                        .iter()
+                        .flat_map(|&i| base_units[i].iter().copied())
+                        .filter(|&idx| grid.has_candidate(idx, digit))
+                        .collect();
+                    let eliminations: Vec<_> = (0..side)
+                        .filter(|i| union & 1 << i != 0)
+                        .flat_map(|i| cover_units[i].iter().copied())
+                        .filter(|idx| !base_cells.contains(idx) && grid.has_candidate(*idx, digit))
+                        .map(|idx| (idx, digit))
+                        .collect();
+                    if !eliminations.is_empty() {
+                        return Some(self.step(technique, base_cells, Vec::new(), eliminations));
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    fn xy_wing(&self, grid: &Grid) -> Option<Step> {
+        let bivalue = |idx: usize| grid.candidates(idx).count_ones() == 2;
+
+        for pivot in (0..self.n_cells()).filter(|&idx| bivalue(idx)) {
+            let pivot_mask = grid.candidates(pivot);
+            for &pincer_a in self.units.peers(pivot).iter().filter(|&&idx| bivalue(idx)) {
+                // The first pincer shares exactly one digit with the pivot, its other digit is
+                // the one that can be eliminated
+                let mask_a = grid.candidates(pincer_a);
+                let shared = mask_a & pivot_mask;
+                if shared.count_ones() != 1 {
+                    continue;
+                }
+                let target = mask_a & !pivot_mask;
+                let mask_b = (pivot_mask & !shared) | target;
+
+                for &pincer_b in self.units.peers(pivot) {
+                    if pincer_b == pincer_a || grid.candidates(pincer_b) != mask_b {
+                        continue;
+                    }
+
+                    let digit = target.trailing_zeros() as u8 + 1;
+                    let eliminations: Vec<_> = (0..self.n_cells())
+                        .filter(|&idx| self.sees(idx, pincer_a) && self.sees(idx, pincer_b))
+                        .filter(|&idx| idx != pivot && grid.has_candidate(idx, digit))
+                        .map(|idx| (idx, digit))
+                        .collect();
+                    if !eliminations.is_empty() {
+                        return Some(self.step(
+                            Technique::XYWing,
+                            [pivot, pincer_a, pincer_b],
+                            Vec::new(),
+                            eliminations,
+                        ));
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    fn simple_coloring(&self, grid: &Grid) -> Option<Step> {
+        for digit in 1..=self.side() as u8 {
+            // Conjugate pairs: units in which the digit can only go into two cells
+            let links: Vec<(usize, usize)> = self
+                .units
+                .units()
+                .iter()
+                .filter_map(|unit| {
+                    let mut positions = unit.iter().filter(|&&idx| grid.has_candidate(idx, digit));
+                    let (a, b) = (*positions.next()?, *positions.next()?);
+                    positions.next().is_none().then_some((a, b))
+                })
+                .collect();
+
+            let mut colors: Vec<Option<bool>> = vec![None; self.n_cells()];
+            for &(start, _) in &links {
+                if colors[start].is_some() {
+                    continue;
+                }
+
+                // Color the chain starting at this cell with alternating colors
+                let mut chain = vec![start];
+                colors[start] = Some(true);
+                let mut queue = vec![start];
+                while let Some(idx) = queue.pop() {
+                    let color = colors[idx].unwrap();
+                    for &(a, b) in &links {
+                        let next = match idx {
+                            _ if idx == a => b,
+                            _ if idx == b => a,
+                            _ => continue,
+                        };
+                        if colors[next].is_none() {
+                            colors[next] = Some(!color);
+                            chain.push(next);
+                            queue.push(next);
+                        }
+                    }
+                }
+
+                let with_color = |color: bool| -> Vec<usize> {
+                    chain
+                        .iter()
+                        .copied()
+                        .filter(|&idx| colors[idx] == Some(color))
+                        .collect()
+                };
+                let (on, off) = (with_color(true), with_color(false));
+
+                // Color wrap: two cells of the same color see each other, so that color is false
+                for cells in [&on, &off] {
+                    let wraps = cells
+                        .iter()
+                        .any(|&a| cells.iter().any(|&b| self.sees(a, b)));
+                    if wraps {
+                        let eliminations = cells.iter().map(|&idx| (idx, digit));
+                        return Some(self.step(
+                            Technique::SimpleColoring,
+                            chain,
+                            Vec::new(),
+                            eliminations,
+                        ));
+                    }
+                }
+
+                // Color trap: a cell which sees both colors can't contain the digit
+                let eliminations: Vec<_> = (0..self.n_cells())
+                    .filter(|&idx| grid.has_candidate(idx, digit) && !chain.contains(&idx))
+                    .filter(|&idx| on.iter().any(|&a| self.sees(idx, a)))
+                    .filter(|&idx| off.iter().any(|&b| self.sees(idx, b)))
+                    .map(|idx| (idx, digit))
+                    .collect();
+                if !eliminations.is_empty() {
+                    return Some(self.step(
+                        Technique::SimpleColoring,
+                        chain,
+                        Vec::new(),
+                        eliminations,
+                    ));
+                }
+            }
+        }
+        None
+    }
+}
+
+impl Default for LogicalSolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SudokuSolver for LogicalSolver {
+    fn solve(&self, board: Sudoku) -> Option<Sudoku> {
+        let solution = self.solve_with_steps(board);
+        (solution.outcome == Outcome::Solved).then_some(solution.board)
+    }
+
+    // Puzzles which logic can't finish are counted by searching the rest of the board
+    fn count_solutions(&self, board: &Sudoku, limit: usize) -> usize {
+        if limit == 0 {
+            return 0;
+        }
+        let solution = self.solve_with_steps(board.clone());
+        match solution.outcome {
+            Outcome::Solved => 1,
+            Outcome::Invalid => 0,
+            Outcome::Stuck => AlgorithmXSudokuSolver::for_board(&solution.board)
+                .count_solutions(&solution.board, limit),
+        }
+    }
+
+    // Like `solve`, the solution of a puzzle which logic can't finish is left out
+    fn solve_and_count(
+        &self,
+        board: Sudoku,
+        limit: usize,
+        _metrics: Option<&mut SearchMetrics>,
+    ) -> (Option<Sudoku>, usize) {
+        if limit == 0 {
+            return (None, 0);
+        }
+        let solution = self.solve_with_steps(board);
+        match solution.outcome {
+            Outcome::Solved => (Some(solution.board), 1),
+            Outcome::Invalid => (None, 0),
+            Outcome::Stuck => {
+                let count = AlgorithmXSudokuSolver::for_board(&solution.board)
+                    .count_solutions(&solution.board, limit);
+                (None, count)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sudoku_solver::Uniqueness;
+
+    // Checks that every step of the logical solver agrees with the actual solution
+    fn check_steps(puzzle: &str) -> LogicalSolution {
+        check_board_steps(Sudoku::try_from_str(puzzle).unwrap())
+    }
+
+    fn check_board_steps(sudoku: Sudoku) -> LogicalSolution {
+        let expected = AlgorithmXSudokuSolver::new().solve(sudoku.clone()).unwrap();
+        let solution = LogicalSolver::new().solve_with_steps(sudoku);
+
+        for step in &solution.steps {
+            for &(row, col, digit) in &step.placements {
+                assert_eq!(expected.get(row, col), digit, "{}", step);
+            }
+            for &(row, col, digit) in &step.eliminations {
+                assert_ne!(expected.get(row, col), digit, "{}", step);
+            }
+        }
+        solution
+    }
+
+    #[test]
+    fn test_techniques() {
+        // Puzzles which can be solved with the given technique as the hardest one
+        let puzzles = [
+            (
+                "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79",
+                Technique::HiddenSingle,
+            ),
+            (
+                "7..1523........92....3.....1....47.8.......6............9...5.6.4.9.7...8....6.1.",
+                Technique::NakedSingle,
+            ),
+            (
+                "4.....8.5.3..........7......2.....6.....8.4......1.......6.3.7.5..2.....1.4......",
+                Technique::LockedCandidates,
+            ),
+            (
+                "......52..8.4......3...9...5.1...6..2..7........3.....6...1..........7.4.......3.",
+                Technique::NakedPair,
+            ),
+            (
+                "45.....3....8.1....9...........5..9.2..7.....8.........1..4..........7.2...6..8..",
+                Technique::HiddenPair,
+            ),
+            (
+                "1.....3.8.7.4..............2.3.1...........958.........5.6...7.....8.2...4.......",
+                Technique::NakedTriple,
+            ),
+            (
+                "4...7.1....19.46.5.....1......7....2..2.3....847..6....14...8.6.2....3..6...9....",
+                Technique::HiddenTriple,
+            ),
+            (
+                "....7.9...2.6.....5.....3...4.....217...3................2.1.8.9.....7.....4.....",
+                Technique::XWing,
+            ),
+            (
+                ".6.5.4.3.1...9...8.........9...5...6.4.6.2.7.7...4...5.........4...8...1.5.2.3.4.",
+                Technique::Swordfish,
+            ),
+            (
+                "..9.....3.....9...7.....5.6..65..4.....3......28......3..75.6..6...........12.3.8",
+                Technique::XYWing,
+            ),
+            (
+                "....14....3....2...7..........9...3.6.1.............8.2.....1.4....5.6.....7.8...",
+                Technique::SimpleColoring,
+            ),
+        ];
+
+        for (puzzle, technique) in puzzles {
+            let solution = check_steps(puzzle);
+            assert_eq!(solution.outcome, Outcome::Solved);
+            assert!(solution.board.is_solved());
+            let hardest = solution.steps.iter().map(|step| step.technique).max();
+            assert_eq!(hardest, Some(technique));
+        }
+    }
+
+    #[test]
+    fn test_other_sizes() {
+        let solution = check_steps("1.3..4.2.3..2.4.");
+        assert_eq!(solution.outcome, Outcome::Solved);
+        assert_eq!(solution.board.serialize(), "1234341243212143");
+
+        let solution = check_steps(".BC126....4A64..9..51..3..3.8.A1.B..492.B3.A..58.15.7..C3.....A341..B...1.6.3.24C.8B......C947.1C2.8.....9...A7...32..1...12.B..9....C.4..862AB7");
+        assert_eq!(solution.outcome, Outcome::Solved);
+    }
+
+    #[test]
+    fn test_variants() {
+        let mut sudoku = Sudoku::try_from_str(
+            "1.3.5.7.9.7.8.9.1.8.4.2.3.5.5.3.4.9.4.6.8.5.7.8.5.2.4.5.2.1......................",
+        )
+        .unwrap();
+        sudoku.set_variants(&[Variant::Diagonal, Variant::AntiKing]);
+        let solution = check_board_steps(sudoku.clone());
+        assert_eq!(solution.outcome, Outcome::Solved);
+        assert!(solution.board.is_solved());
+
+        // Without the variant rules the puzzle has many solutions
+        sudoku.set_variants(&[]);
+        assert_eq!(check_board_steps(sudoku).outcome, Outcome::Stuck);
+    }
+
+    #[test]
+    fn test_jigsaw() {
+        let sudoku =
+            Sudoku::try_from_jigsaw_str(include_str!("../resources/jigsaw_sudoku.txt")).unwrap();
+        let solution = check_board_steps(sudoku.clone());
+        assert_eq!(solution.outcome, Outcome::Stuck);
+        assert!(!solution.steps.is_empty());
+
+        // With a few more givens the regions are enough to solve it
+        let mut easier = Sudoku::try_from_str(
+            "9.716.8.225.4.973.8.4.5.9.7.927.8.6.3.8.2.1.514.235.7.4.3.725.9.258.7.9.7.6.9.284",
+        )
+        .unwrap();
+        easier.set_regions(sudoku.regions().cloned()).unwrap();
+        let solution = check_board_steps(easier);
+        assert_eq!(solution.outcome, Outcome::Solved);
+        assert!(solution.board.is_solved());
+    }
+
+    #[test]
+    fn test_stuck() {
+        let solution = check_steps(
+            "48.3............71.2.......7.5....6....2..8.............1.76...3.....4......5....",
+        );
+        assert_eq!(solution.outcome, Outcome::Stuck);
+        assert!(!solution.board.is_solved());
+    }
+
+    #[test]
+    fn test_count_solutions() {
+        let solver = LogicalSolver::new();
+        let solved = Sudoku::try_from_str(
+            "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79",
+        )
+        .unwrap();
+        assert_eq!(solver.is_unique(&solved), Uniqueness::Unique);
+        assert_eq!(solver.count_solutions(&solved, 0), 0);
+
+        // Logic gets stuck on this one, the rest is searched
+        let stuck = Sudoku::try_from_str(
+            "48.3............71.2.......7.5....6....2..8.............1.76...3.....4......5....",
+        )
+        .unwrap();
+        assert_eq!(solver.is_unique(&stuck), Uniqueness::Unique);
+        let ambiguous = Sudoku::try_from_str(&".".repeat(81)).unwrap();
+        assert_eq!(solver.count_solutions(&ambiguous, 3), 3);
+
+        let invalid = Sudoku::try_from_str(
+            "11...............................................................................",
+        )
+        .unwrap();
+        assert_eq!(solver.is_unique(&invalid), Uniqueness::None);
+    }
+
+    #[test]
+    fn test_invalid() {
+        let solver = LogicalSolver::new();
+        let sudoku = Sudoku::try_from_str(
+            "11...............................................................................",
+        )
+        .unwrap();
+        assert_eq!(solver.solve_with_steps(sudoku).outcome, Outcome::Invalid);
+    }
+
+    #[test]
+    fn test_step_display() {
+        let step = Step {
+            technique: Technique::XWing,
+            cells: vec![(0, 0), (0, 4), (3, 0), (3, 4)],
+            placements: Vec::new(),
+            eliminations: vec![(5, 0, 7), (8, 4, 7)],
+        };
+        assert_eq!(step.to_string(), "X-Wing: r6c1<>7 r9c5<>7");
+    }
+}
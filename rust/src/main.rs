@@ -1,31 +1,66 @@
-use anyhow::Result;
-use clap::Parser;
-use indicatif::ProgressIterator;
+use anyhow::{bail, Context, Result};
+use clap::{
+    builder::{PossibleValue, PossibleValuesParser, TypedValueParser},
+    Parser, Subcommand,
+};
+use indicatif::{ProgressBar, ProgressIterator};
+use rand::{rngs::StdRng, SeedableRng};
+use serde::Serialize;
 use std::{
+    collections::BTreeMap,
     fmt::{self},
-    path::PathBuf,
-    time::Instant,
+    fs::File,
+    io::{self, BufWriter, IsTerminal, Write},
+    ops::ControlFlow,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc,
+    },
+    thread,
+    time::{Duration, Instant},
 };
-use sudoku::Sudoku;
 use thiserror::Error;
 
-use sudoku_solver::SudokuSolver;
-
-use crate::algorithm_x_solver::AlgorithmXSudokuSolver;
+use sudoku_solver::{
+    algorithm_x::{SearchMetrics, SearchObserver},
+    algorithm_x_solver::{AlgorithmXSudokuSolver, AlgorithmXWorkspaceSolver, MatrixStrategy},
+    backtracking_solver::BacktrackingSolver,
+    format::Format,
+    game::{Game, SolutionPath},
+    generator::Generator,
+    grading::{Grader, Level},
+    killer_solver::KillerSolver,
+    killer_sudoku::KillerSudoku,
+    puzzle_file::{Puzzle, PuzzleFile, Rejected},
+    regions::Regions,
+    sudoku::{BoardSize, Sudoku, Variant},
+    SolveOutcome, SudokuError, SudokuSolver, TraceEvent,
+};
 
-mod algorithm_x;
-pub mod algorithm_x_solver;
-mod backtracking_solver;
 mod stats;
-mod sudoku;
-mod sudoku_solver;
+mod tui;
 
 const DEFAULT_INPUT_FILE: &str =
     concat!(env!("CARGO_MANIFEST_DIR"), "/resources/bench_sudokus.txt");
+const DEFAULT_KILLER_INPUT_FILE: &str =
+    concat!(env!("CARGO_MANIFEST_DIR"), "/resources/killer_sudoku.txt");
+
+// Written instead of a solution for sudokus without one
+const NO_SOLUTION_MARKER: &str = "# no solution";
+// Written instead of a solution for sudokus skipped with `--skip-invalid`
+const INVALID_MARKER: &str = "# invalid sudoku";
+// Written after the solution of sudokus with more than one solution
+const MULTIPLE_SOLUTIONS_MARKER: &str = "# multiple solutions";
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
+#[command(args_conflicts_with_subcommands = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Path to the file containing sudokus to solve
     #[arg(default_value = DEFAULT_INPUT_FILE)]
     input: PathBuf,
@@ -33,6 +68,83 @@ struct Args {
     /// Solver type to use
     #[arg(short, long, default_value_t = SolverType::AlgorithmX)]
     solver: SolverType,
+
+    /// How the Algorithm X solver sets up the exact cover matrix of every sudoku
+    #[arg(long, default_value_t = MatrixStrategy::FullMatrix, value_parser = names(&MatrixStrategy::ALL))]
+    strategy: MatrixStrategy,
+
+    /// Format of the input file, detected from its extension or content if omitted
+    #[arg(long, value_parser = names(&Format::ALL))]
+    input_format: Option<Format>,
+
+    /// Variant rules all sudokus have to satisfy, can be given multiple times
+    #[arg(long = "variant", value_name = "VARIANT", value_parser = names(&Variant::ALL))]
+    variants: Vec<Variant>,
+
+    /// Path to a region map which replaces the boxes of all sudokus, for jigsaw sudokus
+    #[arg(long)]
+    regions: Option<PathBuf>,
+
+    /// Skip invalid sudokus instead of stopping, they are listed at the end
+    #[arg(long)]
+    skip_invalid: bool,
+
+    /// Number of threads solving sudokus in parallel, 0 uses one per CPU core
+    #[arg(short, long, default_value_t = 1)]
+    jobs: usize,
+
+    /// Write the solutions to this file, one per input line, or to stdout for `-`
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Collect search statistics like nodes, backtracks and cover times, which slows solving
+    #[arg(long)]
+    search_stats: bool,
+
+    /// Format of the written solutions, grids are followed by an empty line
+    #[arg(long, default_value_t = Format::Line, value_parser = names(&Format::ALL))]
+    output_format: Format,
+
+    /// Format of the statistics, JSON is written to stdout and all other messages to stderr
+    #[arg(long, default_value_t = ReportFormat::Text)]
+    format: ReportFormat,
+
+    /// Number of slowest sudokus listed in the statistics
+    #[arg(long, default_value_t = 5)]
+    slowest: usize,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Generate random sudokus with a unique solution
+    Generate(GenerateArgs),
+    /// Rate sudokus by the techniques needed to solve them
+    Grade(GradeArgs),
+    /// Solve killer sudokus
+    Killer(KillerArgs),
+    /// Print every step of solving a sudoku as JSON lines
+    Trace(PuzzleArgs),
+    /// Play a sudoku in the terminal, with hints and the solution path of the solver
+    Play(PuzzleArgs),
+}
+
+#[derive(clap::Args)]
+struct GenerateArgs {
+    /// Number of sudokus to generate
+    #[arg(short = 'n', long, default_value_t = 1)]
+    count: usize,
+
+    /// Seed for the random number generator, a random seed is used if omitted
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Stop removing clues once this many are left, by default the puzzles are minimal
+    #[arg(short, long, default_value_t = 0)]
+    clues: usize,
+
+    /// Size of the boxes as rows x columns, e.g. 2x3 for a 6x6 sudoku
+    #[arg(long, default_value_t = BoardSize::CLASSIC)]
+    box_size: BoardSize,
 }
 
 #[derive(clap::ValueEnum, Clone, Debug)]
@@ -41,6 +153,32 @@ enum SolverType {
     AlgorithmX,
 }
 
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum ReportFormat {
+    Text,
+    Json,
+}
+
+impl fmt::Display for ReportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReportFormat::Text => write!(f, "text"),
+            ReportFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+// The statistics of a run written with `--format json`
+#[derive(Serialize)]
+struct Report<'a> {
+    solver: String,
+    jobs: usize,
+    solved: usize,
+    invalid: usize,
+    statistics: Option<&'a stats::Statistics>,
+    search: Option<&'a stats::SearchStatistics>,
+}
+
 impl fmt::Display for SolverType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -50,6 +188,22 @@ impl fmt::Display for SolverType {
     }
 }
 
+// The result of solving a single sudoku
+struct Solved {
+    duration: Duration,
+    solution: Solution,
+    // The metrics of the search, if they were asked for
+    metrics: Option<SearchMetrics>,
+}
+
+// The outcome of solving a sudoku, as it is written to the output
+enum Solution {
+    Unique(Sudoku),
+    // One of the solutions
+    Multiple(Sudoku),
+    None,
+}
+
 #[derive(Debug, Error)]
 enum SolverError {
     #[error("No solution found for sudoku:\n{0}")]
@@ -58,52 +212,615 @@ enum SolverError {
     WrongSolution(Sudoku),
 }
 
+#[derive(clap::Args)]
+struct GradeArgs {
+    /// Path to the file containing sudokus to grade
+    #[arg(default_value = DEFAULT_INPUT_FILE)]
+    input: PathBuf,
+
+    /// Sort the sudokus from easiest to hardest
+    #[arg(long)]
+    sort: bool,
+
+    /// Only print sudokus of at least this level
+    #[arg(long, value_parser = names(&Level::ALL))]
+    min_level: Option<Level>,
+
+    /// Only print sudokus of at most this level
+    #[arg(long, value_parser = names(&Level::ALL))]
+    max_level: Option<Level>,
+
+    /// Format of the input file, detected from its extension or content if omitted
+    #[arg(long, value_parser = names(&Format::ALL))]
+    input_format: Option<Format>,
+
+    /// Variant rules all sudokus have to satisfy, can be given multiple times
+    #[arg(long = "variant", value_name = "VARIANT", value_parser = names(&Variant::ALL))]
+    variants: Vec<Variant>,
+
+    /// Path to a region map which replaces the boxes of all sudokus, for jigsaw sudokus
+    #[arg(long)]
+    regions: Option<PathBuf>,
+
+    /// Skip invalid sudokus instead of stopping, they are listed at the end
+    #[arg(long)]
+    skip_invalid: bool,
+}
+
+#[derive(clap::Args)]
+struct KillerArgs {
+    /// Path to the file containing killer sudokus, separated by blank lines
+    #[arg(default_value = DEFAULT_KILLER_INPUT_FILE)]
+    input: PathBuf,
+}
+
+#[derive(clap::Args)]
+struct PuzzleArgs {
+    /// Path to the file containing the sudoku
+    #[arg(default_value = DEFAULT_INPUT_FILE)]
+    input: PathBuf,
+
+    /// Number of the sudoku in the file, starting at 1
+    #[arg(short = 'n', long, default_value_t = 1)]
+    puzzle: usize,
+
+    /// Solver type to use
+    #[arg(short, long, default_value_t = SolverType::AlgorithmX)]
+    solver: SolverType,
+
+    /// Format of the input file, detected from its extension or content if omitted
+    #[arg(long, value_parser = names(&Format::ALL))]
+    input_format: Option<Format>,
+
+    /// Variant rules the sudoku has to satisfy, can be given multiple times
+    #[arg(long = "variant", value_name = "VARIANT", value_parser = names(&Variant::ALL))]
+    variants: Vec<Variant>,
+
+    /// Path to a region map which replaces the boxes of the sudoku, for jigsaw sudokus
+    #[arg(long)]
+    regions: Option<PathBuf>,
+}
+
+// Parses the options of the library types by their displayed names, which are listed in the
+// help like the values of a `clap::ValueEnum`
+fn names<T>(values: &'static [T]) -> impl TypedValueParser<Value = T>
+where
+    T: fmt::Display + FromStr<Err = SudokuError> + Clone + Send + Sync + 'static,
+{
+    PossibleValuesParser::new(
+        values
+            .iter()
+            .map(|value| PossibleValue::new(value.to_string())),
+    )
+    .try_map(|name| name.parse::<T>())
+}
+
 fn main() -> Result<()> {
     let args = Args::parse();
 
+    match args.command {
+        Some(Command::Generate(generate_args)) => generate_sudokus(generate_args),
+        Some(Command::Grade(grade_args)) => grade_sudokus(grade_args),
+        Some(Command::Killer(killer_args)) => solve_killer_sudokus(killer_args),
+        Some(Command::Trace(puzzle_args)) => trace_sudoku(puzzle_args),
+        Some(Command::Play(puzzle_args)) => play_sudoku(puzzle_args),
+        None => solve_sudokus(args),
+    }
+}
+
+fn generate_sudokus(args: GenerateArgs) -> Result<()> {
+    let rng = match args.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_os_rng(),
+    };
+    let mut generator = Generator::with_size(rng, args.box_size);
+
+    for _ in 0..args.count {
+        println!("{}", generator.puzzle_with_clues(args.clues).serialize());
+    }
+
+    Ok(())
+}
+
+// Reads the sudokus of a puzzle file and applies the variants and regions to all of them.
+// Sudokus with contradictory givens are invalid, so they are rejected before the search.
+fn read_sudokus(
+    input: &Path,
+    format: Option<Format>,
+    variants: &[Variant],
+    regions: Option<&Path>,
+    skip_invalid: bool,
+) -> Result<PuzzleFile> {
+    let regions = regions.map(Regions::from_file).transpose()?;
+    let mut file = PuzzleFile::read_as(input, format, skip_invalid)?;
+    for mut puzzle in std::mem::take(&mut file.puzzles) {
+        puzzle.sudoku.set_variants(variants);
+        puzzle
+            .sudoku
+            .set_regions(regions.clone())
+            .with_context(|| format!("{}:{}", input.display(), puzzle.line))?;
+        match puzzle.sudoku.validate() {
+            Ok(()) => file.puzzles.push(puzzle),
+            Err(error) if skip_invalid => file.rejected.push(Rejected {
+                line: puzzle.line,
+                error,
+            }),
+            Err(error) => bail!("{}:{}: {}", input.display(), puzzle.line, error),
+        }
+    }
+    file.rejected.sort_by_key(|rejected| rejected.line);
+    Ok(file)
+}
+
+// Lists the sudokus skipped with `--skip-invalid` on stderr
+fn report_rejected(input: &Path, rejected: &[Rejected]) {
+    if rejected.is_empty() {
+        return;
+    }
+    for Rejected { line, error } in rejected {
+        eprintln!("{}:{}: {}", input.display(), line, error);
+    }
+    eprintln!("Skipped {} invalid sudokus.", rejected.len());
+}
+
+fn grade_sudokus(args: GradeArgs) -> Result<()> {
+    let file = read_sudokus(
+        &args.input,
+        args.input_format,
+        &args.variants,
+        args.regions.as_deref(),
+        args.skip_invalid,
+    )?;
+    report_rejected(&args.input, &file.rejected);
+
+    // Sudokus without a solution have no difficulty, they are only reported
+    let grader = Grader::new();
+    let mut unsolvable = 0;
+    let mut graded = file
+        .puzzles
+        .into_iter()
+        .progress()
+        .filter_map(|Puzzle { line, sudoku }| match grader.grade(&sudoku) {
+            Some(difficulty) => Some((sudoku, difficulty)),
+            None => {
+                eprintln!("{}:{}: no solution", args.input.display(), line);
+                unsolvable += 1;
+                None
+            }
+        })
+        .filter(|(_, difficulty)| args.min_level.is_none_or(|min| difficulty.level >= min))
+        .filter(|(_, difficulty)| args.max_level.is_none_or(|max| difficulty.level <= max))
+        .collect::<Vec<_>>();
+
+    if unsolvable > 0 {
+        eprintln!("Skipped {} sudokus without a solution.", unsolvable);
+    }
+    if args.sort {
+        graded.sort_by(|(_, a), (_, b)| a.rating.total_cmp(&b.rating));
+    }
+
+    for (sudoku, difficulty) in graded {
+        let hardest = difficulty
+            .hardest
+            .map_or_else(|| "-".to_string(), |technique| technique.to_string());
+        println!(
+            "{} {:.2} {} {}",
+            sudoku.serialize(),
+            difficulty.rating,
+            difficulty.level,
+            hardest
+        );
+    }
+
+    Ok(())
+}
+
+fn solve_killer_sudokus(args: KillerArgs) -> Result<()> {
     let content = std::fs::read_to_string(args.input)?;
 
-    let sudokus = content
-        .lines()
-        .map(Sudoku::try_from_str)
-        .collect::<Result<Vec<_>>>()?;
+    // Every puzzle spans several lines, puzzles are separated by blank lines
+    let mut blocks = vec![String::new()];
+    for line in content.lines() {
+        if line.trim().is_empty() {
+            blocks.push(String::new());
+        } else if let Some(block) = blocks.last_mut() {
+            block.push_str(line);
+            block.push('\n');
+        }
+    }
+
+    let puzzles = blocks
+        .iter()
+        .filter(|block| block.lines().any(|line| !line.trim().starts_with('#')))
+        .map(|block| KillerSudoku::try_from_str(block))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let solver = KillerSolver {};
+    for puzzle in puzzles {
+        let now = Instant::now();
+        let solution = solver.solve(&puzzle);
+        let duration = now.elapsed();
 
-    let solver: Box<dyn SudokuSolver> = match args.solver {
+        let solution = solution.ok_or_else(|| SolverError::NoSolution(puzzle.sudoku().clone()))?;
+        if !solution.is_solved() {
+            return Err(SolverError::WrongSolution(puzzle.sudoku().clone()).into());
+        }
+        println!("{} {:.2?}", solution.sudoku().serialize(), duration);
+    }
+
+    Ok(())
+}
+
+// Reads the sudoku picked with `--puzzle` from the input file
+fn read_puzzle(args: &PuzzleArgs) -> Result<Puzzle> {
+    let mut file = read_sudokus(
+        &args.input,
+        args.input_format,
+        &args.variants,
+        args.regions.as_deref(),
+        false,
+    )?;
+    let n_puzzles = file.puzzles.len();
+    match args.puzzle.checked_sub(1).filter(|&idx| idx < n_puzzles) {
+        Some(idx) => Ok(file.puzzles.swap_remove(idx)),
+        None => bail!(
+            "There is no sudoku {} in {}, it has {}",
+            args.puzzle,
+            args.input.display(),
+            n_puzzles
+        ),
+    }
+}
+
+// Solves the sudoku with the given solver and reports every step to the observer
+fn trace(
+    solver_type: &SolverType,
+    sudoku: &Sudoku,
+    observer: &mut impl SearchObserver<TraceEvent>,
+) -> SolveOutcome {
+    match solver_type {
         SolverType::Backtracking => {
-            println!("Using Backtracking solver.\n");
-            Box::new(backtracking_solver::BacktrackingSolver {})
+            BacktrackingSolver::for_board(sudoku).trace(sudoku.clone(), observer)
         }
         SolverType::AlgorithmX => {
-            println!("Using Algorithm X solver.\n");
-            Box::new(AlgorithmXSudokuSolver::new())
+            AlgorithmXSudokuSolver::for_board(sudoku).trace(sudoku.clone(), observer)
+        }
+    }
+}
+
+fn trace_sudoku(args: PuzzleArgs) -> Result<()> {
+    let puzzle = read_puzzle(&args)?;
+
+    // The search stops at the first error writing an event, which is reported then
+    let mut output = BufWriter::new(io::stdout().lock());
+    let mut result = Ok(());
+    let mut observer = |event: TraceEvent| {
+        result = serde_json::to_writer(&mut output, &event)
+            .map_err(anyhow::Error::from)
+            .and_then(|_| Ok(writeln!(output)?));
+        match result {
+            Ok(()) => ControlFlow::Continue(()),
+            Err(_) => ControlFlow::Break(()),
         }
     };
+    let outcome = trace(&args.solver, &puzzle.sudoku, &mut observer);
+    result?;
+    output.flush()?;
 
-    println!("Starting to solve sudokus.\n");
+    if outcome.solution().is_none() {
+        eprintln!("No solution found for sudoku at line {}.", puzzle.line);
+    }
+    Ok(())
+}
 
-    let durations = sudokus
-        .into_iter()
-        .progress()
-        .map(|sudoku| solve_and_time_sudoku(solver.as_ref(), sudoku))
-        .collect::<Result<Vec<_>>>()?;
+fn play_sudoku(args: PuzzleArgs) -> Result<()> {
+    if !io::stdout().is_terminal() {
+        bail!("Playing a sudoku needs an interactive terminal");
+    }
+    let puzzle = read_puzzle(&args)?;
+
+    // The solution path is replayed from the recorded steps, and hints check against the
+    // solution the solver found
+    let mut events = Vec::new();
+    let solution = trace(&args.solver, &puzzle.sudoku, &mut events).solution();
+    let title = format!(
+        "Sudoku {} of {} (line {}), solved with the {} solver",
+        args.puzzle,
+        args.input.display(),
+        puzzle.line,
+        args.solver
+    );
+    let game = Game::new(puzzle.sudoku.clone(), solution);
+    let path = SolutionPath::new(puzzle.sudoku, events);
+    tui::run(tui::App::new(title, game, path))?;
+    Ok(())
+}
+
+fn solve_sudokus(args: Args) -> Result<()> {
+    let file = read_sudokus(
+        &args.input,
+        args.input_format,
+        &args.variants,
+        args.regions.as_deref(),
+        args.skip_invalid,
+    )?;
+    let sudokus: Vec<Sudoku> = file.puzzles.iter().map(|p| p.sudoku.clone()).collect();
+
+    // Solutions or JSON written to stdout must not be mixed up with the other messages
+    let to_stdout = args.output.as_deref() == Some(Path::new("-"));
+    let json = args.format == ReportFormat::Json;
+    if to_stdout && json {
+        bail!("Solutions and JSON statistics can't both be written to stdout");
+    }
+    let info = |message: &str| match to_stdout || json {
+        true => eprintln!("{}", message),
+        false => println!("{}", message),
+    };
+
+    match args.solver {
+        SolverType::Backtracking => info("Using Backtracking solver.\n"),
+        SolverType::AlgorithmX => info(&format!(
+            "Using Algorithm X solver with the {} strategy.\n",
+            args.strategy
+        )),
+    }
+
+    let mut output: Option<Box<dyn Write>> = match &args.output {
+        None => None,
+        Some(_) if to_stdout => Some(Box::new(io::stdout())),
+        Some(path) => Some(Box::new(BufWriter::new(File::create(path).with_context(
+            || format!("Failed to create output file {}", path.display()),
+        )?))),
+    };
+
+    let jobs = match args.jobs {
+        0 => thread::available_parallelism().map_or(1, usize::from),
+        jobs => jobs,
+    };
 
-    let duration_stats = stats::Statistics::from_durations(&durations)?;
+    info("Starting to solve sudokus.\n");
 
-    println!("Statistics: {}", duration_stats);
+    // Skipped lines get a marker in the output, so it still lines up with the input
+    let mut rejected = file
+        .rejected
+        .iter()
+        .map(|rejected| rejected.line)
+        .peekable();
+    let mut write_rejected = |output: &mut dyn Write, before: usize| -> Result<()> {
+        while rejected.next_if(|&line| line < before).is_some() {
+            write_marker(output, args.output_format, INVALID_MARKER)?;
+        }
+        Ok(())
+    };
+
+    let now = Instant::now();
+    let mut durations = Vec::with_capacity(sudokus.len());
+    let mut search_total = SearchMetrics::default();
+    solve_in_parallel(
+        &args.solver,
+        args.strategy,
+        &sudokus,
+        jobs,
+        output.is_some(),
+        args.search_stats,
+        |solved| {
+            let line = file.puzzles[durations.len()].line;
+            durations.push(solved.duration);
+            if let Some(metrics) = &solved.metrics {
+                search_total.add(metrics);
+            }
+            match &mut output {
+                Some(output) => {
+                    write_rejected(output, line)?;
+                    write_solution(output, args.output_format, &solved.solution)
+                }
+                None => Ok(()),
+            }
+        },
+    )?;
+    if let Some(output) = &mut output {
+        write_rejected(output, usize::MAX)?;
+        output.flush()?;
+    }
+    let elapsed = now.elapsed();
+    report_rejected(&args.input, &file.rejected);
+
+    // An empty input, or one whose sudokus were all rejected, leaves nothing to time
+    let duration_stats = if durations.is_empty() {
+        None
+    } else {
+        Some(
+            stats::Statistics::from_durations(&durations)?
+                .with_elapsed(elapsed)
+                .with_slowest(args.slowest, |idx| file.puzzles[idx].line),
+        )
+    };
+    let search_stats = args.search_stats.then(|| {
+        let solver = create_solver(&args.solver, args.strategy, sudokus.first());
+        stats::SearchStatistics::new(search_total, durations.len(), |col| solver.column_name(col))
+    });
+
+    if json {
+        let report = Report {
+            solver: args.solver.to_string(),
+            jobs,
+            solved: durations.len(),
+            invalid: file.rejected.len(),
+            statistics: duration_stats.as_ref(),
+            search: search_stats.as_ref(),
+        };
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    match duration_stats {
+        Some(duration_stats) => info(&format!("Statistics: {}", duration_stats)),
+        None => {
+            info("No sudokus solved.\n");
+            return Ok(());
+        }
+    }
+    if let Some(search_stats) = search_stats {
+        info(&format!("Search: {}", search_stats));
+    }
 
     Ok(())
 }
 
-fn solve_and_time_sudoku(solver: &dyn SudokuSolver, sudoku: Sudoku) -> Result<std::time::Duration> {
+fn create_solver(
+    solver_type: &SolverType,
+    strategy: MatrixStrategy,
+    first: Option<&Sudoku>,
+) -> Box<dyn SudokuSolver> {
+    match solver_type {
+        SolverType::Backtracking => {
+            Box::new(first.map_or_else(BacktrackingSolver::new, BacktrackingSolver::for_board))
+        }
+        SolverType::AlgorithmX => {
+            let solver = first.map_or_else(
+                AlgorithmXSudokuSolver::new,
+                AlgorithmXSudokuSolver::for_board,
+            );
+            match strategy {
+                MatrixStrategy::SubMatrix => Box::new(solver),
+                MatrixStrategy::FullMatrix => Box::new(AlgorithmXWorkspaceSolver::new(solver)),
+            }
+        }
+    }
+}
+
+// Solves the sudokus on `jobs` threads, each with its own solver. The threads take the next
+// unsolved sudoku until none are left, while `on_result` is called with the results in the
+// order of the input as soon as they are available. Stops at the first error.
+fn solve_in_parallel(
+    solver_type: &SolverType,
+    strategy: MatrixStrategy,
+    sudokus: &[Sudoku],
+    jobs: usize,
+    check_uniqueness: bool,
+    search_stats: bool,
+    mut on_result: impl FnMut(Solved) -> Result<()>,
+) -> Result<()> {
+    let progress = ProgressBar::new(sudokus.len() as u64);
+    let next = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+
+    let result = thread::scope(|scope| {
+        for _ in 0..jobs.clamp(1, sudokus.len().max(1)) {
+            let sender = sender.clone();
+            let (next, progress) = (&next, &progress);
+            scope.spawn(move || {
+                let solver = create_solver(solver_type, strategy, sudokus.first());
+                loop {
+                    let idx = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(sudoku) = sudokus.get(idx) else {
+                        break;
+                    };
+                    let result = solve_and_time_sudoku(
+                        solver.as_ref(),
+                        sudoku.clone(),
+                        check_uniqueness,
+                        search_stats,
+                    );
+                    progress.inc(1);
+                    if sender.send((idx, result)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(sender);
+
+        // Results arrive in any order, so they wait here until all earlier ones are handled
+        let mut pending = BTreeMap::new();
+        let mut next_idx = 0;
+        for (idx, result) in receiver.iter() {
+            pending.insert(idx, result);
+            while let Some(result) = pending.remove(&next_idx) {
+                next_idx += 1;
+                if let Err(err) = result.and_then(&mut on_result) {
+                    // Make the workers stop at their next sudoku
+                    next.store(sudokus.len(), Ordering::Relaxed);
+                    return Err(err);
+                }
+            }
+        }
+        Ok(())
+    });
+    progress.finish();
+    result
+}
+
+fn solve_and_time_sudoku(
+    solver: &dyn SudokuSolver,
+    sudoku: Sudoku,
+    check_uniqueness: bool,
+    search_stats: bool,
+) -> Result<Solved> {
+    // Checking uniqueness takes the solution from a search for a second one
     let now = Instant::now();
-    let solution = solver.solve(sudoku.clone());
+    let mut metrics = search_stats.then(SearchMetrics::default);
+    let (solution, count) = match (check_uniqueness, &mut metrics) {
+        (true, metrics) => solver.solve_and_count(sudoku.clone(), 2, metrics.as_mut()),
+        (false, Some(metrics)) => {
+            let (solution, solve_metrics) = solver.solve_with_metrics(sudoku.clone());
+            *metrics = solve_metrics;
+            (solution, 1)
+        }
+        (false, None) => (solver.solve(sudoku.clone()), 1),
+    };
     let duration = now.elapsed();
+    let solved = |solution| Solved {
+        duration,
+        solution,
+        metrics,
+    };
 
-    let solution = solution.ok_or_else(|| SolverError::NoSolution(sudoku.clone()))?;
+    // Unsolvable sudokus are only reported in the output, a benchmark run fails on them
+    let Some(solution) = solution else {
+        if check_uniqueness {
+            return Ok(solved(Solution::None));
+        }
+        return Err(SolverError::NoSolution(sudoku).into());
+    };
 
     if !solution.is_solved() {
         return Err(SolverError::WrongSolution(sudoku).into());
     }
 
-    Ok(duration)
+    if count > 1 {
+        return Ok(solved(Solution::Multiple(solution)));
+    }
+    Ok(solved(Solution::Unique(solution)))
+}
+
+fn write_solution(output: &mut dyn Write, format: Format, solution: &Solution) -> Result<()> {
+    let sudoku = match solution {
+        Solution::Unique(sudoku) | Solution::Multiple(sudoku) => sudoku,
+        Solution::None => return write_marker(output, format, NO_SOLUTION_MARKER),
+    };
+
+    let text = format.write(sudoku);
+    match (format, solution) {
+        (Format::Line, Solution::Multiple(_)) => {
+            writeln!(output, "{} {}", text.trim_end(), MULTIPLE_SOLUTIONS_MARKER)?;
+            return Ok(());
+        }
+        (_, Solution::Multiple(_)) => writeln!(output, "{}", MULTIPLE_SOLUTIONS_MARKER)?,
+        _ => {}
+    }
+    write!(output, "{}", text)?;
+    if format != Format::Line {
+        writeln!(output)?;
+    }
+    Ok(())
+}
+
+// Writes a marker in place of a solution, on its own line or followed by an empty line like a grid
+fn write_marker(output: &mut dyn Write, format: Format, marker: &str) -> Result<()> {
+    writeln!(output, "{}", marker)?;
+    if format != Format::Line {
+        writeln!(output)?;
+    }
+    Ok(())
 }
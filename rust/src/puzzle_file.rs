@@ -0,0 +1,142 @@
+use std::path::Path;
+
+use crate::{
+    error::{Result, SudokuError},
+    format::{parse_all, Format},
+    sudoku::Sudoku,
+};
+
+/// A sudoku read from a puzzle file.
+#[derive(Debug, Clone)]
+pub struct Puzzle {
+    /// The number of the line the sudoku starts on, starting at 1
+    pub line: usize,
+    pub sudoku: Sudoku,
+}
+
+/// A sudoku of a puzzle file which isn't valid.
+#[derive(Debug)]
+pub struct Rejected {
+    /// The number of the line the error was found on, starting at 1
+    pub line: usize,
+    pub error: SudokuError,
+}
+
+/// The sudokus of a puzzle file in one of the [`Format`]s.
+///
+/// Blank lines are skipped, and everything after a `#` is a comment, so comments can take up
+/// a whole line or follow a sudoku. Leading and trailing whitespace is ignored.
+#[derive(Debug, Default)]
+pub struct PuzzleFile {
+    pub puzzles: Vec<Puzzle>,
+    /// The invalid sudokus which were skipped, always empty unless invalid ones are skipped
+    pub rejected: Vec<Rejected>,
+}
+
+impl PuzzleFile {
+    /// Parses the content of a puzzle file, its format is detected with [`Format::detect`].
+    /// With `skip_invalid` sudokus which can't be parsed are collected in
+    /// [`PuzzleFile::rejected`], otherwise the first one is returned as an error.
+    pub fn parse(content: &str, skip_invalid: bool) -> Result<PuzzleFile> {
+        PuzzleFile::parse_as(content, Format::detect(content), skip_invalid)
+    }
+
+    /// Parses the content of a puzzle file in the given format, like [`PuzzleFile::parse`].
+    pub fn parse_as(content: &str, format: Format, skip_invalid: bool) -> Result<PuzzleFile> {
+        PuzzleFile::parse_from(content, format, None, skip_invalid)
+    }
+
+    /// Reads a puzzle file like [`PuzzleFile::parse`], errors point at the `file:line`. The
+    /// format is taken from the `.sdk` and `.ss` extensions before it is detected.
+    pub fn read(path: impl AsRef<Path>, skip_invalid: bool) -> Result<PuzzleFile> {
+        PuzzleFile::read_as(path, None, skip_invalid)
+    }
+
+    /// Reads a puzzle file like [`PuzzleFile::read`], in the given format if there is one.
+    pub fn read_as(
+        path: impl AsRef<Path>,
+        format: Option<Format>,
+        skip_invalid: bool,
+    ) -> Result<PuzzleFile> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path).map_err(|source| SudokuError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let format = format
+            .or_else(|| Format::from_extension(path))
+            .unwrap_or_else(|| Format::detect(&content));
+        PuzzleFile::parse_from(&content, format, Some(path), skip_invalid)
+    }
+
+    // Parses the content, errors point at the line and the file if there is one
+    fn parse_from(
+        content: &str,
+        format: Format,
+        path: Option<&Path>,
+        skip_invalid: bool,
+    ) -> Result<PuzzleFile> {
+        let mut file = PuzzleFile::default();
+        for (line, sudoku) in parse_all(content, format) {
+            match sudoku {
+                Ok(sudoku) => file.puzzles.push(Puzzle { line, sudoku }),
+                Err(error) if skip_invalid => file.rejected.push(Rejected { line, error }),
+                Err(error) => {
+                    let error = Box::new(error);
+                    return Err(match path {
+                        Some(path) => SudokuError::InFile {
+                            path: path.to_path_buf(),
+                            line,
+                            error,
+                        },
+                        None => SudokuError::AtLine { line, error },
+                    });
+                }
+            }
+        }
+        Ok(file)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUZZLES: &str = "
+# Puzzles with comments in between
+
+4.....8.5.3..........7......2.....6.....8.4......1.......6.3.7.5..2.....1.4......
+  1.3_ *.2. 34.. ..4. # indented, with a trailing comment
+  52...6.........7.13...........4..8..6......5...........418.........3..2...87..x..
+1234
+";
+
+    #[test]
+    fn test_parse() {
+        let err = PuzzleFile::parse(PUZZLES, false).unwrap_err();
+        assert!(matches!(err.inner(), SudokuError::InvalidCellCount(19)));
+        assert_eq!(
+            err.to_string(),
+            "line 5: Invalid sudoku string length: expected a square number of cells like 81, got 19"
+        );
+
+        let file = PuzzleFile::parse(PUZZLES, true).unwrap();
+        assert_eq!(file.puzzles.len(), 1);
+        assert_eq!(file.puzzles[0].line, 4);
+        assert_eq!(file.rejected.len(), 3);
+
+        // Whitespace is only allowed around the sudoku
+        assert_eq!(file.rejected[0].line, 5);
+        assert!(file.rejected[0].error.to_string().contains("length"));
+        assert_eq!(file.rejected[1].line, 6);
+        assert_eq!(
+            file.rejected[1].error.to_string(),
+            "Invalid character 'x' at column 81: expected a digit of a 3x3 sudoku or one of the \
+             blanks 0 . _ *"
+        );
+        assert_eq!(file.rejected[2].line, 7);
+
+        let file = PuzzleFile::parse("  1.3_*.2.34....4. # indented", false).unwrap();
+        assert_eq!(file.puzzles[0].sudoku.serialize(), "1.3...2.34....4.");
+    }
+}
@@ -0,0 +1,217 @@
+use std::{fmt, path::Path};
+
+use crate::{
+    error::{Result, SudokuError},
+    sudoku::BoardSize,
+};
+
+/// Assigns every cell of a board to one of its regions, which replace the boxes of a classic
+/// sudoku in jigsaw sudokus.
+///
+/// A region map is written as a grid of region symbols, either on a single line or with one
+/// line per row. Any symbols can be used, as long as there are as many distinct ones as the
+/// board has rows. Regions are numbered in the order they first appear in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Regions {
+    side: usize,
+    ids: Vec<u8>,
+    // The cells of every region, in row major order
+    cells: Vec<Vec<(usize, usize)>>,
+}
+
+impl Regions {
+    /// The regular boxes of a board of the given size.
+    pub fn boxes(size: BoardSize) -> Regions {
+        let side = size.side();
+        let ids = (0..size.n_cells())
+            .map(|idx| size.box_idx(idx / side, idx % side) as u8)
+            .collect();
+        Regions::with_ids(side, ids)
+    }
+
+    // Creates a region map from region ids which are already numbered from 0
+    fn with_ids(side: usize, ids: Vec<u8>) -> Regions {
+        let mut cells = vec![Vec::new(); side];
+        for (idx, &id) in ids.iter().enumerate() {
+            if let Some(region) = cells.get_mut(id as usize) {
+                region.push((idx / side, idx % side));
+            }
+        }
+        Regions { side, ids, cells }
+    }
+
+    /// Creates a region map from one region id per cell, in row major order. Every region has
+    /// to be connected and contain as many cells as the board has rows.
+    pub fn from_ids(ids: &[usize]) -> Result<Regions> {
+        let side = ids.len().isqrt();
+        if side * side != ids.len() || !(1..=BoardSize::MAX_SIDE).contains(&side) {
+            return Err(SudokuError::InvalidRegions(format!(
+                "expected a square number of cells like 81, got {}",
+                ids.len()
+            )));
+        }
+
+        // Number the regions in the order they first appear in
+        let mut symbols = Vec::new();
+        let ids: Vec<u8> = ids
+            .iter()
+            .map(
+                |&id| match symbols.iter().position(|&symbol| symbol == id) {
+                    Some(region) => region as u8,
+                    None => {
+                        symbols.push(id);
+                        (symbols.len() - 1) as u8
+                    }
+                },
+            )
+            .collect();
+        if symbols.len() != side {
+            return Err(SudokuError::InvalidRegions(format!(
+                "expected {} regions, got {}",
+                side,
+                symbols.len()
+            )));
+        }
+
+        let regions = Regions::with_ids(side, ids);
+        for region in 0..side {
+            let cells = regions.cells(region);
+            if cells.len() != side {
+                return Err(SudokuError::InvalidRegions(format!(
+                    "region {} has {} cells instead of {}",
+                    region + 1,
+                    cells.len(),
+                    side
+                )));
+            }
+            if !regions.is_connected(cells) {
+                return Err(SudokuError::InvalidRegions(format!(
+                    "region {} is not connected",
+                    region + 1
+                )));
+            }
+        }
+        Ok(regions)
+    }
+
+    /// Parses a region map written on a single line or as a block with one line per row.
+    /// Whitespace and lines starting with `#` are ignored.
+    pub fn try_from_str(s: &str) -> Result<Regions> {
+        let ids: Vec<usize> = s
+            .lines()
+            .filter(|line| !line.trim_start().starts_with('#'))
+            .flat_map(str::chars)
+            .filter(|c| !c.is_whitespace())
+            .map(|c| c as usize)
+            .collect();
+        Regions::from_ids(&ids)
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Regions> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path).map_err(|source| SudokuError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Regions::try_from_str(&content)
+    }
+
+    /// The number of rows, columns and regions
+    pub fn side(&self) -> usize {
+        self.side
+    }
+
+    /// The index of the region containing the given cell
+    pub fn region(&self, row: usize, col: usize) -> usize {
+        self.ids[row * self.side + col] as usize
+    }
+
+    /// The region of every cell in row major order
+    pub fn ids(&self) -> &[u8] {
+        &self.ids
+    }
+
+    /// The cells of the region with the given index, in row major order
+    pub fn cells(&self, region: usize) -> &[(usize, usize)] {
+        &self.cells[region]
+    }
+
+    // Checks that all cells can be reached from the first one by orthogonal steps
+    fn is_connected(&self, cells: &[(usize, usize)]) -> bool {
+        let mut reached = vec![cells[0]];
+        let mut next = 0;
+        while let Some(&(row, col)) = reached.get(next) {
+            next += 1;
+            for cell in cells {
+                if row.abs_diff(cell.0) + col.abs_diff(cell.1) == 1 && !reached.contains(cell) {
+                    reached.push(*cell);
+                }
+            }
+        }
+        reached.len() == cells.len()
+    }
+}
+
+impl fmt::Display for Regions {
+    /// Formats the region map as a block with one line per row, regions are numbered from 1.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in self.ids.chunks(self.side) {
+            let line: String = row
+                .iter()
+                .map(|&id| BoardSize::digit_to_char(id + 1))
+                .collect();
+            writeln!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JIGSAW: &str = "
+        111222333
+        111222333
+        111222333
+        444555666
+        444555666
+        444555666
+        777888999
+        777888999
+        777888999
+    ";
+
+    #[test]
+    fn test_parse() -> Result<()> {
+        let regions = Regions::try_from_str(JIGSAW)?;
+        assert_eq!(regions, Regions::boxes(BoardSize::CLASSIC));
+        assert_eq!(regions.region(4, 7), 5);
+        assert_eq!(regions.cells(8)[0], (6, 6));
+        assert_eq!(Regions::try_from_str(&regions.to_string())?, regions);
+
+        // Any symbols can be used, the regions are numbered in the order they appear in
+        let single_line = "aabbaabbccddccdd";
+        let regions = Regions::try_from_str(single_line)?;
+        assert_eq!(regions.side(), 4);
+        assert_eq!(regions, Regions::boxes(BoardSize::new(2, 2)?));
+
+        let irregular = Regions::try_from_str("1112122233343444")?;
+        assert_eq!(irregular.cells(0), vec![(0, 0), (0, 1), (0, 2), (1, 0)]);
+        assert_eq!(irregular.region(1, 1), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid() {
+        // Wrong number of cells
+        assert!(Regions::try_from_str("112211223344334").is_err());
+        // Too many regions
+        assert!(Regions::try_from_str("1122112233443345").is_err());
+        // Regions of the wrong size
+        assert!(Regions::try_from_str("1112112233443344").is_err());
+        // A region which isn't connected
+        assert!(Regions::try_from_str("1221122133443344").is_err());
+    }
+}
@@ -1,39 +1,159 @@
-use anyhow::Result;
+use serde::{Serialize, Serializer};
 use std::{fmt, time::Duration};
+use sudoku_solver::algorithm_x::SearchMetrics;
+use thiserror::Error;
 
+#[derive(Debug, Error)]
+pub enum StatsError {
+    #[error("No durations to calculate statistics of.")]
+    NoDurations,
+}
+
+// The width of the longest bar of the histogram
+const HISTOGRAM_WIDTH: usize = 40;
+
+/// Timing statistics of a run, in JSON all durations are given in nanoseconds.
+#[derive(Serialize)]
 pub struct Statistics {
+    count: usize,
+    #[serde(rename = "avg_ns", serialize_with = "nanos")]
     avg: Duration,
+    #[serde(rename = "std_dev_ns", serialize_with = "nanos")]
+    std_dev: Duration,
+    #[serde(rename = "min_ns", serialize_with = "nanos")]
     min: Duration,
+    #[serde(rename = "median_ns", serialize_with = "nanos")]
+    median: Duration,
+    #[serde(rename = "p90_ns", serialize_with = "nanos")]
+    p90: Duration,
+    #[serde(rename = "p99_ns", serialize_with = "nanos")]
+    p99: Duration,
+    #[serde(rename = "p99_9_ns", serialize_with = "nanos")]
+    p99_9: Duration,
+    #[serde(rename = "max_ns", serialize_with = "nanos")]
     max: Duration,
-    std_dev: Duration,
+    #[serde(rename = "total_ns", serialize_with = "nanos")]
     total: Duration,
+    // The wall clock time, which is shorter than the total if sudokus were solved in parallel
+    #[serde(rename = "elapsed_ns", serialize_with = "optional_nanos")]
+    elapsed: Option<Duration>,
+    histogram: Vec<Bucket>,
+    slowest: Vec<Slow>,
+    #[serde(skip)]
+    durations: Vec<Duration>,
+}
+
+/// The number of durations from `from` up to but excluding `to`.
+#[derive(Serialize)]
+struct Bucket {
+    #[serde(rename = "from_ns", serialize_with = "nanos")]
+    from: Duration,
+    #[serde(rename = "to_ns", serialize_with = "nanos")]
+    to: Duration,
+    count: usize,
+}
+
+/// One of the slowest sudokus, its index starts at 0 and its line at 1.
+#[derive(Serialize)]
+struct Slow {
+    index: usize,
+    line: usize,
+    #[serde(rename = "duration_ns", serialize_with = "nanos")]
+    duration: Duration,
 }
 
 impl Statistics {
-    pub fn from_durations(values: &[Duration]) -> Result<Statistics> {
-        if values.len() < 2 {
-            anyhow::bail!("At least two durations are required to calculate standard deviation.");
+    pub fn from_durations(values: &[Duration]) -> Result<Statistics, StatsError> {
+        if values.is_empty() {
+            return Err(StatsError::NoDurations);
         }
 
         let avg = values.iter().cloned().sum::<Duration>() / values.len() as u32;
-        let min = values.iter().min().unwrap();
-        let max = values.iter().max().unwrap();
+        // A single duration doesn't deviate from anything
         let variance = values
             .iter()
             .map(|&x| (x.as_secs_f64() - avg.as_secs_f64()).powi(2))
             .sum::<f64>()
-            / (values.len() - 1) as f64;
+            / (values.len() - 1).max(1) as f64;
         let std_dev = Duration::from_secs_f64(variance.sqrt());
         let total = values.iter().sum();
 
+        let mut sorted = values.to_vec();
+        sorted.sort();
+        // The nearest rank percentile, given in tenths of a percent
+        let percentile = |permille: usize| {
+            let rank = (permille * sorted.len()).div_ceil(1000);
+            sorted[rank.max(1) - 1]
+        };
+
         Ok(Statistics {
+            count: values.len(),
             avg,
-            min: *min,
-            max: *max,
             std_dev,
+            min: sorted[0],
+            median: percentile(500),
+            p90: percentile(900),
+            p99: percentile(990),
+            p99_9: percentile(999),
+            max: sorted[sorted.len() - 1],
             total,
+            elapsed: None,
+            histogram: histogram(&sorted),
+            slowest: Vec::new(),
+            durations: values.to_vec(),
         })
     }
+
+    /// Adds the wall clock time it took to get all durations.
+    pub fn with_elapsed(mut self, elapsed: Duration) -> Statistics {
+        self.elapsed = Some(elapsed);
+        self
+    }
+
+    /// Adds the `n` slowest durations, `line` gives the input line of the duration at an index.
+    pub fn with_slowest(mut self, n: usize, line: impl Fn(usize) -> usize) -> Statistics {
+        let mut indices: Vec<usize> = (0..self.durations.len()).collect();
+        indices.sort_by(|&a, &b| self.durations[b].cmp(&self.durations[a]).then(a.cmp(&b)));
+        self.slowest = indices
+            .into_iter()
+            .take(n)
+            .map(|index| Slow {
+                index,
+                line: line(index),
+                duration: self.durations[index],
+            })
+            .collect();
+        self
+    }
+}
+
+// Counts sorted durations in buckets which double in size, so a few slow outliers don't squash
+// all others into a single bucket. Empty buckets between the fastest and slowest are kept.
+fn histogram(sorted: &[Duration]) -> Vec<Bucket> {
+    let bucket = |duration: &Duration| (duration.as_nanos().max(1)).ilog2();
+    let first = bucket(&sorted[0]);
+    let last = bucket(&sorted[sorted.len() - 1]);
+    (first..=last)
+        .map(|exponent| Bucket {
+            from: Duration::from_nanos(1 << exponent),
+            to: Duration::from_nanos(1 << (exponent + 1)),
+            count: sorted.iter().filter(|d| bucket(d) == exponent).count(),
+        })
+        .collect()
+}
+
+fn nanos<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u128(duration.as_nanos())
+}
+
+fn optional_nanos<S: Serializer>(
+    duration: &Option<Duration>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match duration {
+        Some(duration) => nanos(duration, serializer),
+        None => serializer.serialize_none(),
+    }
 }
 
 impl fmt::Display for Statistics {
@@ -42,6 +162,164 @@ impl fmt::Display for Statistics {
             f,
             "avg: {:.2?}(+/-{:.2?}), min: {:.2?}, max: {:.2?}, total: {:.2?}",
             self.avg, self.std_dev, self.min, self.max, self.total
-        )
+        )?;
+        if let Some(elapsed) = self.elapsed {
+            write!(f, ", elapsed: {:.2?}", elapsed)?;
+        }
+        write!(
+            f,
+            "\nmedian: {:.2?}, p90: {:.2?}, p99: {:.2?}, p99.9: {:.2?}",
+            self.median, self.p90, self.p99, self.p99_9
+        )?;
+
+        let most = self.histogram.iter().map(|b| b.count).max().unwrap_or(0);
+        for bucket in &self.histogram {
+            // Every non-empty bucket gets a bar, however short
+            let bar = (bucket.count * HISTOGRAM_WIDTH).div_ceil(most.max(1));
+            write!(
+                f,
+                "\n{:>10.2?} - {:>10.2?} |{:<width$}| {}",
+                bucket.from,
+                bucket.to,
+                "#".repeat(bar),
+                bucket.count,
+                width = HISTOGRAM_WIDTH
+            )?;
+        }
+
+        if !self.slowest.is_empty() {
+            let slowest: Vec<String> = self
+                .slowest
+                .iter()
+                .map(|slow| {
+                    format!(
+                        "#{} at line {}: {:.2?}",
+                        slow.index, slow.line, slow.duration
+                    )
+                })
+                .collect();
+            write!(f, "\nslowest: {}", slowest.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+// The number of most often chosen columns that are shown
+const TOP_COLUMNS: usize = 5;
+
+/// The search metrics of all sudokus of a run, in JSON all times are given in nanoseconds.
+#[derive(Serialize)]
+pub struct SearchStatistics {
+    #[serde(skip)]
+    count: usize,
+    nodes: u64,
+    backtracks: u64,
+    max_depth: usize,
+    #[serde(rename = "cover_ns", serialize_with = "nanos")]
+    cover_time: Duration,
+    #[serde(rename = "uncover_ns", serialize_with = "nanos")]
+    uncover_time: Duration,
+    // The names of the most often chosen columns, with how often they were chosen
+    most_chosen: Vec<(String, u64)>,
+}
+
+impl SearchStatistics {
+    /// Takes the summed up metrics of `count` searches, `column_name` names their columns.
+    pub fn new(
+        total: SearchMetrics,
+        count: usize,
+        column_name: impl Fn(usize) -> String,
+    ) -> SearchStatistics {
+        let mut columns: Vec<(usize, u64)> = total
+            .column_selections
+            .iter()
+            .copied()
+            .enumerate()
+            .filter(|&(_, selections)| selections > 0)
+            .collect();
+        columns.sort_by(|(a_col, a), (b_col, b)| b.cmp(a).then(a_col.cmp(b_col)));
+        let most_chosen = columns
+            .into_iter()
+            .take(TOP_COLUMNS)
+            .map(|(col, selections)| (column_name(col), selections))
+            .collect();
+
+        SearchStatistics {
+            count,
+            nodes: total.nodes,
+            backtracks: total.backtracks,
+            max_depth: total.max_depth,
+            cover_time: total.cover_time,
+            uncover_time: total.uncover_time,
+            most_chosen,
+        }
+    }
+}
+
+impl fmt::Display for SearchStatistics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let count = self.count.max(1) as f64;
+        write!(
+            f,
+            "nodes: {} (avg {:.1}), backtracks: {} (avg {:.1}), max depth: {}, cover: {:.2?}, \
+             uncover: {:.2?}",
+            self.nodes,
+            self.nodes as f64 / count,
+            self.backtracks,
+            self.backtracks as f64 / count,
+            self.max_depth,
+            self.cover_time,
+            self.uncover_time
+        )?;
+        if !self.most_chosen.is_empty() {
+            let columns: Vec<String> = self
+                .most_chosen
+                .iter()
+                .map(|(name, selections)| format!("{} ({})", name, selections))
+                .collect();
+            write!(f, ", most chosen: {}", columns.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_statistics() -> Result<(), StatsError> {
+        assert!(matches!(
+            Statistics::from_durations(&[]),
+            Err(StatsError::NoDurations)
+        ));
+
+        let single = Statistics::from_durations(&[Duration::from_micros(5)])?;
+        assert_eq!(single.std_dev, Duration::ZERO);
+        assert_eq!(single.p99_9, Duration::from_micros(5));
+
+        let durations: Vec<Duration> = (1..=1000).rev().map(Duration::from_micros).collect();
+        let stats = Statistics::from_durations(&durations)?.with_slowest(2, |idx| idx + 1);
+        assert_eq!(stats.min, Duration::from_micros(1));
+        assert_eq!(stats.median, Duration::from_micros(500));
+        assert_eq!(stats.p90, Duration::from_micros(900));
+        assert_eq!(stats.p99, Duration::from_micros(990));
+        assert_eq!(stats.p99_9, Duration::from_micros(999));
+        assert_eq!(stats.max, Duration::from_micros(1000));
+
+        // 1µs to 1ms fall into the buckets from 512ns up to 1.05ms
+        assert_eq!(stats.histogram.len(), 11);
+        assert_eq!(stats.histogram[0].from, Duration::from_nanos(512));
+        assert_eq!(stats.histogram.iter().map(|b| b.count).sum::<usize>(), 1000);
+        assert_eq!(
+            stats
+                .slowest
+                .iter()
+                .map(|slow| (slow.index, slow.line))
+                .collect::<Vec<_>>(),
+            [(0, 1), (1, 2)]
+        );
+
+        Ok(())
     }
 }
@@ -1,41 +1,568 @@
-use anyhow::{bail, Result};
 use ndarray::{iter, prelude::*};
-use std::{collections::HashSet, fmt};
+use std::{fmt, str::FromStr, sync::Arc};
+
+use crate::{
+    error::{Result, SudokuError},
+    regions::Regions,
+    units::Units,
+};
+
+/// The dimensions of a sudoku, given by the size of its boxes.
+///
+/// A board has `box_rows * box_cols` rows, columns and boxes, e.g. 3x3 boxes give the classic
+/// 9x9 sudoku and 2x3 boxes a 6x6 sudoku. Digits above 9 are written as letters starting with
+/// `A`, so a 16x16 sudoku uses `1`-`9` and `A`-`G`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BoardSize {
+    box_rows: usize,
+    box_cols: usize,
+}
+
+impl BoardSize {
+    /// The largest supported number of digits
+    pub const MAX_SIDE: usize = 32;
+
+    pub const CLASSIC: BoardSize = BoardSize {
+        box_rows: 3,
+        box_cols: 3,
+    };
+
+    pub fn new(box_rows: usize, box_cols: usize) -> Result<BoardSize> {
+        if box_rows == 0 || box_cols == 0 || box_rows * box_cols > Self::MAX_SIDE {
+            return Err(SudokuError::InvalidBoxSize { box_rows, box_cols });
+        }
+        Ok(BoardSize { box_rows, box_cols })
+    }
+
+    /// Guesses the board size from the number of cells. Boxes are chosen as square as
+    /// possible, with more columns than rows for rectangular boxes.
+    pub fn from_n_cells(n_cells: usize) -> Option<BoardSize> {
+        let side = n_cells.isqrt();
+        if side * side != n_cells || !(2..=Self::MAX_SIDE).contains(&side) {
+            return None;
+        }
+        let box_rows = (2..=side.isqrt())
+            .rev()
+            .find(|rows| side.is_multiple_of(*rows))?;
+        Some(BoardSize {
+            box_rows,
+            box_cols: side / box_rows,
+        })
+    }
+
+    pub fn box_rows(&self) -> usize {
+        self.box_rows
+    }
+
+    pub fn box_cols(&self) -> usize {
+        self.box_cols
+    }
+
+    /// The number of rows, columns, boxes and digits
+    pub fn side(&self) -> usize {
+        self.box_rows * self.box_cols
+    }
+
+    pub fn n_cells(&self) -> usize {
+        self.side() * self.side()
+    }
+
+    pub fn box_idx(&self, row: usize, col: usize) -> usize {
+        (row / self.box_rows) * self.box_rows + col / self.box_cols
+    }
+
+    /// The character used for a digit, `.` for an empty cell
+    pub fn digit_to_char(digit: u8) -> char {
+        match digit {
+            0 => '.',
+            1..=9 => (digit + b'0') as char,
+            _ => (digit - 10 + b'A') as char,
+        }
+    }
+
+    /// The digit for a character, 0 for the blanks `0`, `.`, `_` and `*`, or `None` if the
+    /// character isn't a digit on this board
+    pub fn char_to_digit(&self, c: char) -> Option<u8> {
+        match c {
+            '.' | '_' | '*' => Some(0),
+            _ => c
+                .to_digit(36)
+                .filter(|&d| d as usize <= self.side())
+                .map(|d| d as u8),
+        }
+    }
+}
+
+impl Default for BoardSize {
+    fn default() -> Self {
+        Self::CLASSIC
+    }
+}
+
+impl fmt::Display for BoardSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}x{}", self.box_rows, self.box_cols)
+    }
+}
+
+impl FromStr for BoardSize {
+    type Err = SudokuError;
+
+    /// Parses a box size like `3x3` or `2x3`.
+    fn from_str(s: &str) -> Result<BoardSize> {
+        let parsed = s
+            .split_once('x')
+            .and_then(|(rows, cols)| Some((rows.trim().parse().ok()?, cols.trim().parse().ok()?)));
+        let Some((box_rows, box_cols)) = parsed else {
+            return Err(SudokuError::InvalidBoxSizeFormat(s.to_string()));
+        };
+        BoardSize::new(box_rows, box_cols)
+    }
+}
+
+/// Extra rules on top of the classic row, column and box constraints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Variant {
+    /// Both main diagonals contain every digit once, also known as X-Sudoku
+    Diagonal,
+    /// Extra boxes, offset by one cell from the regular boxes, contain every digit once
+    Windoku,
+    /// Cells a chess knight's move apart can't contain the same digit
+    AntiKnight,
+    /// Cells a chess king's move apart can't contain the same digit
+    AntiKing,
+}
+
+impl Variant {
+    pub const ALL: [Variant; 4] = [
+        Variant::Diagonal,
+        Variant::Windoku,
+        Variant::AntiKnight,
+        Variant::AntiKing,
+    ];
+
+    /// The additional units of this variant, each of them has to contain every digit once.
+    pub fn units(&self, size: BoardSize) -> Vec<Vec<(usize, usize)>> {
+        let side = size.side();
+        match self {
+            Variant::Diagonal => vec![
+                (0..side).map(|i| (i, i)).collect(),
+                (0..side).map(|i| (i, side - 1 - i)).collect(),
+            ],
+            Variant::Windoku => {
+                // The windows have the shape of a box and are separated by a single cell, on a
+                // 9x9 board this gives the four windows starting at r2c2, r2c6, r6c2 and r6c6
+                let starts = |len: usize| {
+                    (1..side)
+                        .step_by(len + 1)
+                        .take_while(move |start| start + len <= side)
+                };
+                let (box_rows, box_cols) = (size.box_rows(), size.box_cols());
+                starts(box_rows)
+                    .flat_map(|row| starts(box_cols).map(move |col| (row, col)))
+                    .map(|(row, col)| {
+                        (0..side)
+                            .map(|i| (row + i / box_cols, col + i % box_cols))
+                            .collect()
+                    })
+                    .collect()
+            }
+            Variant::AntiKnight | Variant::AntiKing => Vec::new(),
+        }
+    }
+
+    /// The cells which can't contain the same digit as the given cell because of this variant.
+    pub fn neighbours(
+        &self,
+        size: BoardSize,
+        row: usize,
+        col: usize,
+    ) -> impl Iterator<Item = (usize, usize)> {
+        const KNIGHT_MOVES: [(isize, isize); 8] = [
+            (-2, -1),
+            (-2, 1),
+            (-1, -2),
+            (-1, 2),
+            (1, -2),
+            (1, 2),
+            (2, -1),
+            (2, 1),
+        ];
+        const KING_MOVES: [(isize, isize); 8] = [
+            (-1, -1),
+            (-1, 0),
+            (-1, 1),
+            (0, -1),
+            (0, 1),
+            (1, -1),
+            (1, 0),
+            (1, 1),
+        ];
+
+        let moves: &[(isize, isize)] = match self {
+            Variant::AntiKnight => &KNIGHT_MOVES,
+            Variant::AntiKing => &KING_MOVES,
+            Variant::Diagonal | Variant::Windoku => &[],
+        };
+        let side = size.side();
+        moves.iter().filter_map(move |&(d_row, d_col)| {
+            let row = row.checked_add_signed(d_row).filter(|&row| row < side)?;
+            let col = col.checked_add_signed(d_col).filter(|&col| col < side)?;
+            Some((row, col))
+        })
+    }
+}
+
+impl fmt::Display for Variant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Variant::Diagonal => write!(f, "diagonal"),
+            Variant::Windoku => write!(f, "windoku"),
+            Variant::AntiKnight => write!(f, "anti-knight"),
+            Variant::AntiKing => write!(f, "anti-king"),
+        }
+    }
+}
+
+impl FromStr for Variant {
+    type Err = SudokuError;
+
+    /// Parses a variant by its name, like `diagonal` or `anti-knight`.
+    fn from_str(s: &str) -> Result<Variant> {
+        SudokuError::parse_name(s, "variant", &Variant::ALL)
+    }
+}
+
+/// A part of the board which can't contain a digit twice.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unit {
+    Row(usize),
+    Column(usize),
+    /// A box, or a region of a jigsaw sudoku
+    Box(usize),
+    /// A unit or the neighbouring cells of a variant
+    Variant(Variant),
+}
+
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Unit::Row(idx) => write!(f, "row {}", idx + 1),
+            Unit::Column(idx) => write!(f, "column {}", idx + 1),
+            Unit::Box(idx) => write!(f, "box {}", idx + 1),
+            Unit::Variant(variant) => write!(f, "the {} rule", variant),
+        }
+    }
+}
+
+/// A reason why a sudoku has no solution, which is found without searching.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Conflict {
+    /// The same digit is placed in two cells of a unit, given as (row, col)
+    Duplicate {
+        unit: Unit,
+        digit: u8,
+        cells: [(usize, usize); 2],
+    },
+    /// An empty cell without any candidates left
+    DeadCell { row: usize, col: usize },
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Conflict::Duplicate {
+                unit,
+                digit,
+                cells: [(r1, c1), (r2, c2)],
+            } => write!(
+                f,
+                "{} is placed twice in {}, at r{}c{} and r{}c{}",
+                BoardSize::digit_to_char(*digit),
+                unit,
+                r1 + 1,
+                c1 + 1,
+                r2 + 1,
+                c2 + 1
+            ),
+            Conflict::DeadCell { row, col } => {
+                write!(f, "no digit fits into r{}c{}", row + 1, col + 1)
+            }
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct Sudoku {
     board: Array2<u8>,
+    // The size, variants and regions of the board with the units and peers of every cell,
+    // shared by all sudokus with the same layout
+    units: Arc<Units>,
+    // How often every digit is placed in every unit, a counter per digit and unit
+    counts: Vec<u8>,
+    // The digits placed in every unit, as bitmasks
+    placed: Vec<u32>,
+    // The pencil marks of every cell in row major order, empty for filled cells
+    candidates: Vec<u32>,
+}
+
+// The bitmask of a digit in the candidate and unit masks
+fn bit(digit: u8) -> u32 {
+    1 << (digit - 1)
 }
 
 impl Sudoku {
     pub fn new() -> Sudoku {
-        Sudoku {
-            board: Array2::zeros((9, 9)),
+        Sudoku::with_size(BoardSize::CLASSIC)
+    }
+
+    pub fn with_size(size: BoardSize) -> Sudoku {
+        Sudoku::from_board(Array2::zeros((size.side(), size.side())), size)
+    }
+
+    // Creates a sudoku with the given digits and the candidates following from them
+    fn from_board(board: Array2<u8>, size: BoardSize) -> Sudoku {
+        let mut sudoku = Sudoku {
+            board,
+            units: Units::get(size, &[], None),
+            counts: Vec::new(),
+            placed: Vec::new(),
+            candidates: Vec::new(),
+        };
+        sudoku.reset_candidates();
+        sudoku
+    }
+
+    pub fn size(&self) -> BoardSize {
+        self.units.size()
+    }
+
+    /// The variant rules this sudoku has to satisfy, in addition to the classic ones
+    pub fn variants(&self) -> &[Variant] {
+        self.units.variants()
+    }
+
+    pub fn set_variants(&mut self, variants: &[Variant]) {
+        let mut variants = variants.to_vec();
+        variants.sort_unstable();
+        variants.dedup();
+        self.units = Units::get(self.size(), &variants, self.regions());
+        self.reset_candidates();
+    }
+
+    /// The irregular regions of a jigsaw sudoku, `None` if the sudoku uses regular boxes
+    pub fn regions(&self) -> Option<&Regions> {
+        self.units.regions()
+    }
+
+    // The tables of the units and peers of the board
+    pub(crate) fn units(&self) -> &Arc<Units> {
+        &self.units
+    }
+
+    /// Replaces the boxes by the given regions, or restores the regular boxes for `None`.
+    pub fn set_regions(&mut self, regions: Option<Regions>) -> Result<()> {
+        if let Some(regions) = &regions {
+            if regions.side() != self.size().side() {
+                return Err(SudokuError::RegionsMismatch {
+                    regions: regions.side(),
+                    sudoku: self.size().side(),
+                });
+            }
         }
+        self.units = Units::get(self.size(), self.variants(), regions.as_ref());
+        self.reset_candidates();
+        Ok(())
     }
 
     pub fn serialize(&self) -> String {
         self.board
             .iter()
-            .map(|cell| match *cell {
-                0 => '.',
-                _ => (*cell + b'0') as char,
-            })
+            .map(|cell| BoardSize::digit_to_char(*cell))
             .collect()
     }
 
+    /// Parses a sudoku from a single line, the board size is derived from its length.
+    /// Blanks are written as `0`, `.`, `_` or `*`, any other character which isn't a digit of
+    /// the board is rejected.
     pub fn try_from_str(s: &str) -> Result<Sudoku> {
-        if s.len() != 81 {
-            bail!("Invalid sudoku string length: expected 81, got {}", s.len());
-        }
-        let mut board = Array2::zeros((9, 9));
-        s.chars().zip(board.iter_mut()).for_each(|(c, u)| {
-            *u = match c.to_digit(10) {
-                Some(d) => d as u8,
-                None => 0,
+        Sudoku::parse_line(s, None, 1)
+    }
+
+    /// Parses a jigsaw sudoku, given by a line with the sudoku followed by its region map, see
+    /// [`Regions::try_from_str`].
+    pub fn try_from_jigsaw_str(s: &str) -> Result<Sudoku> {
+        let s = s.trim_start();
+        let (sudoku, regions) = s.split_once('\n').unwrap_or((s, ""));
+        let mut sudoku = Sudoku::try_from_str(sudoku.trim())?;
+        sudoku.set_regions(Some(Regions::try_from_str(regions)?))?;
+        Ok(sudoku)
+    }
+
+    /// Parses a pencil-mark grid, as written by [`Sudoku::pencil_marks`].
+    ///
+    /// Every cell is a whitespace separated token in row major order, the board size is derived
+    /// from the number of cells. A single digit is a placed digit, several digits are the
+    /// candidates of an empty cell. A single candidate is written in brackets like `[5]`, and
+    /// `.` or `0` is an empty cell with all candidates allowed by the placed digits. Box
+    /// separators made up of `|`, `-` and `+` are ignored. Candidates which are ruled out by
+    /// placed digits are dropped.
+    pub fn try_from_pencil_marks(s: &str) -> Result<Sudoku> {
+        let tokens: Vec<&str> = s
+            .split(|c: char| c.is_whitespace() || c == '|')
+            .filter(|token| !token.is_empty() && !token.chars().all(|c| "-+".contains(c)))
+            .collect();
+        let Some(size) = BoardSize::from_n_cells(tokens.len()) else {
+            return Err(SudokuError::InvalidPencilMarks(format!(
+                "expected a square number of cells like 81, got {}",
+                tokens.len()
+            )));
+        };
+
+        let mut digits = Vec::with_capacity(tokens.len());
+        let mut marks = Vec::with_capacity(tokens.len());
+        for token in tokens {
+            let (inner, bracketed) = match token.strip_prefix('[') {
+                Some(rest) => match rest.strip_suffix(']') {
+                    Some(inner) => (inner, true),
+                    None => {
+                        return Err(SudokuError::InvalidPencilMarks(format!(
+                            "cell '{}' is missing a ']'",
+                            token
+                        )))
+                    }
+                },
+                None => (token, false),
             };
-        });
-        Ok(Sudoku { board })
+            if !bracketed && (inner == "." || inner == "0") {
+                digits.push(0);
+                marks.push(None);
+                continue;
+            }
+            let mut mask = 0;
+            for c in inner.chars() {
+                match size.char_to_digit(c).unwrap_or(0) {
+                    0 => {
+                        return Err(SudokuError::InvalidPencilMarks(format!(
+                            "'{}' in cell '{}' isn't a digit of a {} sudoku",
+                            c, token, size
+                        )))
+                    }
+                    digit => mask |= bit(digit),
+                }
+            }
+            if !bracketed && mask.count_ones() == 1 && inner.chars().count() == 1 {
+                digits.push(mask.trailing_zeros() as u8 + 1);
+                marks.push(None);
+            } else {
+                digits.push(0);
+                marks.push(Some(mask));
+            }
+        }
+
+        let board = Array2::from_shape_vec((size.side(), size.side()), digits).unwrap();
+        let mut sudoku = Sudoku::from_board(board, size);
+        for (idx, mask) in marks.into_iter().enumerate() {
+            if let Some(mask) = mask {
+                sudoku.set_candidates(idx / size.side(), idx % size.side(), mask);
+            }
+        }
+        Ok(sudoku)
+    }
+
+    /// Formats the board as a pencil-mark grid, with the candidates of every empty cell and
+    /// the placed digits. The columns are aligned and the boxes separated like in the
+    /// [`Display`](fmt::Display) output.
+    pub fn pencil_marks(&self) -> String {
+        let side = self.size().side();
+        let tokens: Vec<String> = self
+            .board
+            .indexed_iter()
+            .map(|((row, col), &cell)| {
+                if cell != 0 {
+                    return BoardSize::digit_to_char(cell).to_string();
+                }
+                let candidates = self.candidates(row, col);
+                let marks: String = (1..=side as u8)
+                    .filter(|&digit| candidates & bit(digit) != 0)
+                    .map(BoardSize::digit_to_char)
+                    .collect();
+                match marks.len() {
+                    2.. => marks,
+                    _ => format!("[{}]", marks),
+                }
+            })
+            .collect();
+        let width = tokens.iter().map(String::len).max().unwrap_or(1);
+
+        // Irregular regions can't be drawn with separators
+        let (box_rows, box_cols) = match self.regions() {
+            Some(_) => (side, side),
+            None => (self.size().box_rows(), self.size().box_cols()),
+        };
+        let separator = vec!["-".repeat((width + 1) * box_cols + 1); side / box_cols].join("+");
+
+        let mut s = String::new();
+        for (i, row) in tokens.chunks(side).enumerate() {
+            let boxes: Vec<String> = row
+                .chunks(box_cols)
+                .map(|cells| {
+                    let cells: Vec<_> = cells
+                        .iter()
+                        .map(|cell| format!("{:<width$}", cell))
+                        .collect();
+                    format!(" {} ", cells.join(" "))
+                })
+                .collect();
+            s.push_str(boxes.join("|").trim_end());
+            s.push('\n');
+            if (i + 1) % box_rows == 0 && i + 1 < side {
+                s.push_str(separator.trim_end_matches(' '));
+                s.push('\n');
+            }
+        }
+        s
+    }
+
+    pub fn try_from_str_with_size(s: &str, size: BoardSize) -> Result<Sudoku> {
+        Sudoku::parse_line(s, Some(size), 1)
+    }
+
+    /// Parses a single line sudoku like [`Sudoku::try_from_str`], the size is derived from the
+    /// length if it isn't given. `first_column` is the column of the first cell in the input,
+    /// so errors can point at the right character of a longer line.
+    pub(crate) fn parse_line(
+        s: &str,
+        size: Option<BoardSize>,
+        first_column: usize,
+    ) -> Result<Sudoku> {
+        let n_cells = s.chars().count();
+        let size = match size {
+            Some(size) if n_cells != size.n_cells() => {
+                return Err(SudokuError::InvalidLength {
+                    expected: size.n_cells(),
+                    got: n_cells,
+                })
+            }
+            Some(size) => size,
+            None => match BoardSize::from_n_cells(n_cells) {
+                Some(size) => size,
+                None => return Err(SudokuError::InvalidCellCount(n_cells)),
+            },
+        };
+
+        let mut board = Array2::zeros((size.side(), size.side()));
+        for (column, (c, cell)) in (first_column..).zip(s.chars().zip(board.iter_mut())) {
+            let Some(digit) = size.char_to_digit(c) else {
+                return Err(SudokuError::InvalidCharacter {
+                    character: c,
+                    column,
+                    size,
+                });
+            };
+            *cell = digit;
+        }
+        Ok(Sudoku::from_board(board, size))
     }
 
     pub fn iter(&self) -> iter::Iter<'_, u8, Dim<[usize; 2]>> {
@@ -51,10 +578,24 @@ impl Sudoku {
     }
 
     pub fn boxes(&self) -> impl Iterator<Item = Array1<&u8>> {
-        self.board
-            .exact_chunks((3, 3))
-            .into_iter()
-            .map(|block| block.into_iter().collect::<Array<_, _>>())
+        let boxes: Vec<_> = match self.regions() {
+            Some(regions) => (0..self.size().side())
+                .map(|region| {
+                    regions
+                        .cells(region)
+                        .iter()
+                        .map(|&(row, col)| &self.board[[row, col]])
+                        .collect()
+                })
+                .collect(),
+            None => self
+                .board
+                .exact_chunks((self.size().box_rows(), self.size().box_cols()))
+                .into_iter()
+                .map(|block| block.into_iter().collect::<Array<_, _>>())
+                .collect(),
+        };
+        boxes.into_iter()
     }
 
     pub fn row(&self, idx: usize) -> ArrayView1<'_, u8> {
@@ -66,15 +607,26 @@ impl Sudoku {
     }
 
     pub fn box_containing(&self, row: usize, col: usize) -> Array1<u8> {
-        let start_row = 3 * (row / 3);
-        let start_col = 3 * (col / 3);
+        if let Some(regions) = self.regions() {
+            return regions
+                .cells(regions.region(row, col))
+                .iter()
+                .map(|&(row, col)| self.get(row, col))
+                .collect();
+        }
+
+        let box_rows = self.size().box_rows();
+        let box_cols = self.size().box_cols();
 
-        let end_row = start_row + 3;
-        let end_col = start_col + 3;
+        let start_row = box_rows * (row / box_rows);
+        let start_col = box_cols * (col / box_cols);
+
+        let end_row = start_row + box_rows;
+        let end_col = start_col + box_cols;
 
         self.board
             .slice(s![start_row..end_row, start_col..end_col])
-            .to_shape(9)
+            .to_shape(self.size().side())
             .unwrap()
             .into_owned()
     }
@@ -83,71 +635,252 @@ impl Sudoku {
         self.board[[row, col]]
     }
 
+    /// The digits of a classic 9x9 sudoku in row major order, `None` for any other size.
+    pub fn to_array(&self) -> Option<[u8; 81]> {
+        if self.size() != BoardSize::CLASSIC {
+            return None;
+        }
+        self.board
+            .iter()
+            .copied()
+            .collect::<Vec<_>>()
+            .try_into()
+            .ok()
+    }
+
+    /// Places a digit, or clears the cell for 0, and updates the candidates of the board.
+    ///
+    /// Placing a digit removes it from the candidates of all cells sharing a constraint with
+    /// the cell. Removing a digit adds it back to those cells where no other placed digit
+    /// rules it out, which also undoes manual eliminations of that digit there.
     pub fn set(&mut self, row: usize, col: usize, val: u8) {
-        self.board[[row, col]] = val
+        let old = self.board[[row, col]];
+        if old == val {
+            return;
+        }
+        self.board[[row, col]] = val;
+
+        let side = self.size().side();
+        let idx = row * side + col;
+        if (1..=side as u8).contains(&old) {
+            for &unit in self.units.cell_units(idx) {
+                let count = &mut self.counts[unit * side + old as usize - 1];
+                *count -= 1;
+                if *count == 0 {
+                    self.placed[unit] &= !bit(old);
+                }
+            }
+            // The digit may still be ruled out in a peer by another unit or a variant neighbour
+            for &peer in self.units.peers(idx) {
+                if self.digit_at(peer) == 0 && self.allowed_digits(peer) & bit(old) != 0 {
+                    self.candidates[peer] |= bit(old);
+                }
+            }
+        }
+
+        if (1..=side as u8).contains(&val) {
+            for &unit in self.units.cell_units(idx) {
+                self.counts[unit * side + val as usize - 1] += 1;
+                self.placed[unit] |= bit(val);
+            }
+            for &peer in self.units.peers(idx) {
+                self.candidates[peer] &= !bit(val);
+            }
+            self.candidates[idx] = 0;
+        } else {
+            self.candidates[idx] = self.allowed_digits(idx);
+        }
     }
 
-    pub fn get_box_idx(&self, row: usize, col: usize) -> usize {
-        (row / 3) * 3 + col / 3
+    /// The candidates of a cell as a bitmask, bit `d - 1` is set if the digit `d` is a
+    /// candidate. Filled cells have no candidates.
+    pub fn candidates(&self, row: usize, col: usize) -> u32 {
+        self.candidates[row * self.size().side() + col]
     }
 
-    pub fn is_valid(&self, row: usize, col: usize, val: u8) -> bool {
-        // Check that val is within [1, 9]
-        if !(1..=9).contains(&val) {
-            return false;
-        }
+    pub fn has_candidate(&self, row: usize, col: usize, digit: u8) -> bool {
+        (1..=self.size().side() as u8).contains(&digit)
+            && self.candidates(row, col) & bit(digit) != 0
+    }
 
-        let is_val = |elem: &u8| *elem == val;
+    /// Replaces the candidates of an empty cell, digits ruled out by placed digits are dropped.
+    pub fn set_candidates(&mut self, row: usize, col: usize, candidates: u32) {
+        if self.get(row, col) == 0 {
+            let idx = row * self.size().side() + col;
+            self.candidates[idx] = candidates & self.allowed_digits(idx);
+        }
+    }
 
-        // Check row constraint
-        if self.row(row).iter().any(is_val) {
+    /// Removes a digit from the candidates of a cell, returns whether it was a candidate.
+    pub fn eliminate(&mut self, row: usize, col: usize, digit: u8) -> bool {
+        if !self.has_candidate(row, col, digit) {
             return false;
         }
+        let idx = row * self.size().side() + col;
+        self.candidates[idx] &= !bit(digit);
+        true
+    }
 
-        // Check col constraint
-        if self.col(col).iter().any(is_val) {
-            return false;
+    /// Recomputes all candidates from the placed digits, which drops all manual eliminations.
+    pub fn reset_candidates(&mut self) {
+        let side = self.size().side();
+        let n_units = self.units.units().len();
+        self.counts = vec![0; n_units * side];
+        self.placed = vec![0; n_units];
+        for (unit_idx, unit) in self.units.units().iter().enumerate() {
+            for &idx in unit {
+                let digit = self.digit_at(idx);
+                if (1..=side as u8).contains(&digit) {
+                    self.counts[unit_idx * side + digit as usize - 1] += 1;
+                    self.placed[unit_idx] |= bit(digit);
+                }
+            }
         }
+        self.candidates = (0..self.size().n_cells())
+            .map(|idx| match self.digit_at(idx) {
+                0 => self.allowed_digits(idx),
+                _ => 0,
+            })
+            .collect();
+    }
 
-        // Check box constraint
-        if self.box_containing(row, col).iter().any(is_val) {
-            return false;
+    /// The digits placed in a row as a bitmask, like the candidates
+    pub fn row_digits(&self, row: usize) -> u32 {
+        self.placed[row]
+    }
+
+    /// The digits placed in a column as a bitmask, like the candidates
+    pub fn col_digits(&self, col: usize) -> u32 {
+        self.placed[self.size().side() + col]
+    }
+
+    /// The digits placed in a box, or region of a jigsaw sudoku, as a bitmask like the
+    /// candidates
+    pub fn box_digits(&self, box_idx: usize) -> u32 {
+        self.placed[2 * self.size().side() + box_idx]
+    }
+
+    // The digit of a cell given by its index in row major order
+    fn digit_at(&self, idx: usize) -> u8 {
+        let side = self.size().side();
+        self.board[[idx / side, idx % side]]
+    }
+
+    // The digits which can be placed into a cell according to the placed digits
+    fn allowed_digits(&self, idx: usize) -> u32 {
+        let side = self.size().side();
+        let mut allowed = u32::MAX >> (u32::BITS as usize - side);
+        for &unit in self.units.cell_units(idx) {
+            allowed &= !self.placed[unit];
+        }
+        for &neighbour in self.units.neighbours(idx) {
+            let digit = self.digit_at(neighbour);
+            if (1..=side as u8).contains(&digit) {
+                allowed &= !bit(digit);
+            }
         }
+        allowed
+    }
 
-        true
+    pub fn get_box_idx(&self, row: usize, col: usize) -> usize {
+        self.units.box_idx(row * self.size().side() + col)
     }
 
-    pub fn is_solved(&self) -> bool {
-        // Check that all cells are in range [0, 9]
-        if self.board.iter().any(|cell| *cell > 9 || *cell == 0) {
-            return false;
+    pub fn is_valid(&self, row: usize, col: usize, val: u8) -> bool {
+        (1..=self.size().side() as u8).contains(&val)
+            && self.allowed_digits(row * self.size().side() + col) & bit(val) != 0
+    }
+
+    /// Checks the placed digits against each other before any search. Every pair of cells
+    /// with the same digit in a unit is a conflict, as is every empty cell without candidates.
+    pub fn validate(&self) -> Result<()> {
+        let side = self.size().side();
+        let mut conflicts = Vec::new();
+
+        for idx in 0..side {
+            let row = (0..side).map(|col| (idx, col));
+            self.find_duplicates(Unit::Row(idx), row, &mut conflicts);
+            let col = (0..side).map(|row| (row, idx));
+            self.find_duplicates(Unit::Column(idx), col, &mut conflicts);
+        }
+        let mut boxes = vec![Vec::with_capacity(side); side];
+        for row in 0..side {
+            for col in 0..side {
+                boxes[self.get_box_idx(row, col)].push((row, col));
+            }
+        }
+        for (idx, cells) in boxes.into_iter().enumerate() {
+            self.find_duplicates(Unit::Box(idx), cells, &mut conflicts);
         }
 
-        // Check row constraints
-        if self
-            .rows()
-            .any(|row| row.iter().collect::<HashSet<_>>().len() != 9)
-        {
-            return false;
+        for &variant in self.variants() {
+            for unit in variant.units(self.size()) {
+                self.find_duplicates(Unit::Variant(variant), unit, &mut conflicts);
+            }
+            for ((row, col), &digit) in self.board.indexed_iter() {
+                // Every pair of neighbours is only reported once, from the earlier cell
+                let later = variant
+                    .neighbours(self.size(), row, col)
+                    .filter(|&cell| cell > (row, col) && self.get(cell.0, cell.1) == digit);
+                for cell in later.take_while(|_| digit != 0) {
+                    conflicts.push(Conflict::Duplicate {
+                        unit: Unit::Variant(variant),
+                        digit,
+                        cells: [(row, col), cell],
+                    });
+                }
+            }
         }
 
-        // Check column constraints
-        if self
-            .cols()
-            .any(|col| col.iter().collect::<HashSet<_>>().len() != 9)
-        {
-            return false;
+        for ((row, col), &digit) in self.board.indexed_iter() {
+            if digit == 0 && self.candidates(row, col) == 0 {
+                conflicts.push(Conflict::DeadCell { row, col });
+            }
         }
 
-        // Check box constraints
-        if self
-            .boxes()
-            .any(|box_| box_.iter().collect::<HashSet<_>>().len() != 9)
-        {
-            return false;
+        match conflicts.is_empty() {
+            true => Ok(()),
+            false => Err(SudokuError::ConflictingGivens(conflicts)),
         }
+    }
 
-        true
+    // Adds a conflict for every pair of cells of the unit with the same digit
+    fn find_duplicates(
+        &self,
+        unit: Unit,
+        cells: impl IntoIterator<Item = (usize, usize)>,
+        conflicts: &mut Vec<Conflict>,
+    ) {
+        let filled: Vec<_> = cells
+            .into_iter()
+            .filter(|&(row, col)| self.get(row, col) != 0)
+            .collect();
+        for (i, &first) in filled.iter().enumerate() {
+            let digit = self.get(first.0, first.1);
+            for &second in filled[i + 1..]
+                .iter()
+                .filter(|cell| self.get(cell.0, cell.1) == digit)
+            {
+                conflicts.push(Conflict::Duplicate {
+                    unit,
+                    digit,
+                    cells: [first, second],
+                });
+            }
+        }
+    }
+
+    pub fn is_solved(&self) -> bool {
+        let side = self.size().side() as u8;
+        // With all cells filled, every unit has to contain every digit exactly once
+        self.board.iter().all(|cell| (1..=side).contains(cell))
+            && self.counts.iter().all(|&count| count == 1)
+            && (0..self.size().n_cells()).all(|idx| {
+                self.units
+                    .neighbours(idx)
+                    .iter()
+                    .all(|&neighbour| self.digit_at(neighbour) != self.digit_at(idx))
+            })
     }
 }
 
@@ -167,7 +900,7 @@ impl<'a> IntoIterator for &'a Sudoku {
 }
 
 impl TryFrom<&str> for Sudoku {
-    type Error = anyhow::Error;
+    type Error = SudokuError;
 
     fn try_from(s: &str) -> Result<Sudoku> {
         Sudoku::try_from_str(s)
@@ -175,15 +908,21 @@ impl TryFrom<&str> for Sudoku {
 }
 
 impl TryFrom<[u8; 81]> for Sudoku {
-    type Error = anyhow::Error;
+    type Error = SudokuError;
 
     fn try_from(board: [u8; 81]) -> Result<Sudoku> {
-        if board.iter().any(|elem| *elem > 9) {
-            bail!("Invalid board");
+        if let Some(idx) = board.iter().position(|elem| *elem > 9) {
+            return Err(SudokuError::ValueOutOfRange {
+                row: idx / 9,
+                col: idx % 9,
+                value: board[idx],
+                max: 9,
+            });
         }
-        Ok(Sudoku {
-            board: Array2::from_shape_vec((9, 9), board.to_vec()).unwrap(),
-        })
+        Ok(Sudoku::from_board(
+            Array2::from_shape_vec((9, 9), board.to_vec()).unwrap(),
+            BoardSize::CLASSIC,
+        ))
     }
 }
 
@@ -193,29 +932,40 @@ impl From<Sudoku> for String {
     }
 }
 
+/// Panics if the sudoku isn't a classic 9x9 sudoku, see [`Sudoku::to_array`] for a conversion
+/// which doesn't.
 impl From<Sudoku> for [u8; 81] {
     fn from(sudoku: Sudoku) -> [u8; 81] {
-        sudoku.board.into_raw_vec_and_offset().0.try_into().unwrap()
+        sudoku
+            .to_array()
+            .unwrap_or_else(|| panic!("only a 9x9 sudoku can be converted, got {}", sudoku.size()))
     }
 }
 
 impl fmt::Display for Sudoku {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let side = self.size().side();
+        // Irregular regions can't be drawn with separators
+        let (box_rows, box_cols) = match self.regions() {
+            Some(_) => (side, side),
+            None => (self.size().box_rows(), self.size().box_cols()),
+        };
+
+        let separator = vec!["-".repeat(box_cols); side / box_cols].join("+");
+
         let mut s = String::new();
         for (i, row) in self.rows().enumerate() {
             for (j, cell) in row.iter().enumerate() {
-                match *cell {
-                    0 => s.push('.'),
-                    _ => s.push((*cell + b'0') as char),
-                }
+                s.push(BoardSize::digit_to_char(*cell));
 
-                if j == 2 || j == 5 {
+                if (j + 1) % box_cols == 0 && j + 1 < side {
                     s.push('|');
                 }
             }
             s.push('\n');
-            if i == 2 || i == 5 {
-                s.push_str("---+---+---\n");
+            if (i + 1) % box_rows == 0 && i + 1 < side {
+                s.push_str(&separator);
+                s.push('\n');
             }
         }
         write!(f, "{}", s)
@@ -262,14 +1012,54 @@ mod tests {
         );
         assert!(!sudoku.is_solved());
 
+        // Other blanks are accepted, but no characters which aren't digits of the board
         let sudoku: Sudoku =
-            "12345678945678912378912345623456789156789123489123456734567891267891234591234567a"
+            "0234567894567891237891234_623456789156789123489123456734567891267891234591234567*"
                 .try_into()?;
         assert_eq!(
             sudoku.serialize(),
-            "12345678945678912378912345623456789156789123489123456734567891267891234591234567."
+            ".234567894567891237891234.623456789156789123489123456734567891267891234591234567."
         );
-        assert!(!sudoku.is_solved());
+        let err = Sudoku::try_from_str(
+            "12345678945678912378912345623456789156789123489123456734567891267891234591234567a",
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("'a' at column 81"));
+        assert!(matches!(
+            err,
+            SudokuError::InvalidCharacter {
+                character: 'a',
+                column: 81,
+                ..
+            }
+        ));
+        assert!(matches!(
+            Sudoku::try_from_str("1234 "),
+            Err(SudokuError::InvalidCellCount(5))
+        ));
+        assert!(matches!(
+            Sudoku::try_from_str_with_size("1234", BoardSize::CLASSIC),
+            Err(SudokuError::InvalidLength {
+                expected: 81,
+                got: 4
+            })
+        ));
+        let mut board = [0; 81];
+        board[10] = 10;
+        assert!(matches!(
+            Sudoku::try_from(board),
+            Err(SudokuError::ValueOutOfRange {
+                row: 1,
+                col: 1,
+                value: 10,
+                max: 9
+            })
+        ));
+
+        board[10] = 5;
+        let sudoku = Sudoku::try_from(board)?;
+        assert_eq!(sudoku.to_array(), Some(board));
+        assert_eq!(<[u8; 81]>::from(sudoku), board);
 
         Ok(())
     }
@@ -350,6 +1140,306 @@ mod tests {
         assert_eq!(sudoku.get(0, 0), 2);
         assert_eq!(sudoku.get(8, 8), 9);
 
+        // The candidates are updated incrementally, which has to agree with recomputing them,
+        // also when digits are placed twice
+        let mut sudoku = Sudoku::new();
+        sudoku.set_variants(&[Variant::Diagonal, Variant::AntiKnight]);
+        let mut state = 1u32;
+        for _ in 0..1000 {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            let idx = (state >> 8) as usize % 81;
+            sudoku.set(idx / 9, idx % 9, (state >> 20) as u8 % 10);
+            let mut expected = sudoku.clone();
+            expected.reset_candidates();
+            assert_eq!(sudoku.counts, expected.counts);
+            assert_eq!(sudoku.placed, expected.placed);
+            assert_eq!(sudoku.candidates, expected.candidates);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_board_sizes() -> Result<()> {
+        assert_eq!(BoardSize::from_n_cells(16), Some(BoardSize::new(2, 2)?));
+        assert_eq!(BoardSize::from_n_cells(36), Some(BoardSize::new(2, 3)?));
+        assert_eq!(BoardSize::from_n_cells(81), Some(BoardSize::CLASSIC));
+        assert_eq!(BoardSize::from_n_cells(144), Some(BoardSize::new(3, 4)?));
+        assert_eq!(BoardSize::from_n_cells(256), Some(BoardSize::new(4, 4)?));
+        assert_eq!(BoardSize::from_n_cells(625), Some(BoardSize::new(5, 5)?));
+        assert_eq!(BoardSize::from_n_cells(49), None);
+        assert_eq!(BoardSize::from_n_cells(80), None);
+        assert_eq!("2x3".parse::<BoardSize>()?, BoardSize::new(2, 3)?);
+        assert!("2x".parse::<BoardSize>().is_err());
+        assert!(BoardSize::new(0, 3).is_err());
+
+        let sudoku: Sudoku = "123456456123231564564231312645645312".try_into()?;
+        assert_eq!(sudoku.size(), BoardSize::new(2, 3)?);
+        assert!(sudoku.is_solved());
+        assert_eq!(sudoku.get_box_idx(3, 4), 3);
+        assert_eq!(
+            sudoku.to_string(),
+            "123|456\n456|123\n---+---\n231|564\n564|231\n---+---\n312|645\n645|312\n"
+        );
+        assert_eq!(sudoku.to_array(), None);
+
+        let mut sudoku = Sudoku::with_size(BoardSize::new(4, 4)?);
+        assert!(sudoku.is_valid(0, 0, 16));
+        assert!(!sudoku.is_valid(0, 0, 17));
+        sudoku.set(0, 0, 16);
+        sudoku.set(15, 15, 10);
+        let serialized = sudoku.serialize();
+        assert_eq!(serialized.len(), 256);
+        assert!(serialized.starts_with("G."));
+        assert!(serialized.ends_with(".A"));
+        assert_eq!(Sudoku::try_from_str(&serialized)?.serialize(), serialized);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jigsaw() -> Result<()> {
+        let mut sudoku = Sudoku::try_from_jigsaw_str(
+            "
+            1.......4..3....
+            1112
+            1222
+            3334
+            3444
+            ",
+        )?;
+        assert_eq!(sudoku.get_box_idx(1, 0), 0);
+        assert_eq!(sudoku.get_box_idx(1, 1), 1);
+        assert_eq!(sudoku.box_containing(3, 1).to_vec(), vec![3, 0, 0, 0]);
+        assert!(!sudoku.is_valid(3, 1, 3));
+        assert!(sudoku.is_valid(3, 1, 2));
+        assert_eq!(sudoku.to_string(), "1...\n....\n4..3\n....\n");
+
+        // A solution which is only valid with regular boxes
+        let mut solution = Sudoku::try_from_str("1234341221434321")?;
+        assert!(solution.is_solved());
+        solution.set_regions(sudoku.regions().cloned())?;
+        assert!(!solution.is_solved());
+
+        // A solution for the regions
+        let solution = Sudoku::try_from_str("1342213442133421")?;
+        let regions = sudoku.regions().cloned();
+        sudoku = solution;
+        sudoku.set_regions(regions)?;
+        assert!(sudoku.is_solved());
+
+        assert!(Sudoku::new()
+            .set_regions(sudoku.regions().cloned())
+            .is_err());
+        assert!(Sudoku::try_from_jigsaw_str("1.......4..3....").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_variants() -> Result<()> {
+        for variant in Variant::ALL {
+            assert_eq!(variant.to_string().parse::<Variant>()?, variant);
+        }
+        assert!(matches!(
+            "knight".parse::<Variant>(),
+            Err(SudokuError::UnknownName {
+                kind: "variant",
+                ..
+            })
+        ));
+
+        let units = Variant::Windoku.units(BoardSize::CLASSIC);
+        assert_eq!(units.len(), 4);
+        assert_eq!(units[3][0], (5, 5));
+        assert_eq!(units[3][8], (7, 7));
+        assert_eq!(
+            Variant::AntiKnight
+                .neighbours(BoardSize::CLASSIC, 0, 1)
+                .collect::<Vec<_>>(),
+            vec![(1, 3), (2, 0), (2, 2)]
+        );
+
+        // A valid classic solution which breaks both diagonals
+        let mut sudoku: Sudoku =
+            "123456789456789123789123456234567891567891234891234567345678912678912345912345678"
+                .try_into()?;
+        sudoku.set_variants(&[Variant::Diagonal, Variant::Diagonal]);
+        assert_eq!(sudoku.variants(), &[Variant::Diagonal]);
+        assert!(!sudoku.is_solved());
+
+        // Digits on the diagonal may not repeat
+        let mut sudoku = Sudoku::new();
+        sudoku.set_variants(&[Variant::Diagonal]);
+        sudoku.set(0, 0, 5);
+        assert!(!sudoku.is_valid(4, 4, 5));
+        assert!(sudoku.is_valid(4, 5, 5));
+
+        // Neither may digits a king's move apart
+        let mut sudoku = Sudoku::new();
+        sudoku.set_variants(&[Variant::AntiKing]);
+        sudoku.set(2, 2, 5);
+        assert!(!sudoku.is_valid(3, 3, 5));
+        assert!(sudoku.is_valid(4, 4, 5));
+
+        Ok(())
+    }
+
+    fn conflicts(error: &SudokuError) -> Vec<Conflict> {
+        match error {
+            SudokuError::ConflictingGivens(conflicts) => conflicts.clone(),
+            error => panic!("Expected conflicting givens, got {}", error),
+        }
+    }
+
+    #[test]
+    fn test_validate() -> Result<()> {
+        let mut sudoku = Sudoku::try_from_str("1.3...2.34....4.")?;
+        assert!(sudoku.validate().is_ok());
+
+        // The second 3 clashes with the ones in its row and column
+        sudoku.set(2, 2, 3);
+        let err = sudoku.validate().unwrap_err();
+        assert!(matches!(&err, SudokuError::ConflictingGivens(conflicts) if conflicts.len() == 2));
+        assert_eq!(
+            conflicts(&err),
+            vec![
+                Conflict::Duplicate {
+                    unit: Unit::Row(2),
+                    digit: 3,
+                    cells: [(2, 0), (2, 2)]
+                },
+                Conflict::Duplicate {
+                    unit: Unit::Column(2),
+                    digit: 3,
+                    cells: [(0, 2), (2, 2)]
+                },
+            ]
+        );
+        assert_eq!(
+            err.to_string(),
+            "Contradictory sudoku: 3 is placed twice in row 3, at r3c1 and r3c3, 3 is placed \
+             twice in column 3, at r1c3 and r3c3"
+        );
+
+        // Without duplicates, but the 1, 2, 3 and 4 around r1c4 leave nothing for it
+        let sudoku = Sudoku::try_from_str("12....4....3....")?;
+        let err = sudoku.validate().unwrap_err();
+        assert_eq!(conflicts(&err), vec![Conflict::DeadCell { row: 0, col: 3 }]);
+        assert_eq!(
+            err.to_string(),
+            "Contradictory sudoku: no digit fits into r1c4"
+        );
+
+        let mut sudoku = Sudoku::try_from_str("1.........1.....")?;
+        assert!(sudoku.validate().is_ok());
+        sudoku.set_variants(&[Variant::Diagonal, Variant::AntiKing]);
+        let conflicts = conflicts(&sudoku.validate().unwrap_err());
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(
+            conflicts[0],
+            Conflict::Duplicate {
+                unit: Unit::Variant(Variant::Diagonal),
+                digit: 1,
+                cells: [(0, 0), (2, 2)]
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_candidates() -> Result<()> {
+        let mut sudoku = Sudoku::try_from_str("1.......4..3....")?;
+        assert_eq!(sudoku.candidates(0, 0), 0);
+        assert_eq!(sudoku.candidates(0, 1), 0b1110);
+        assert_eq!(sudoku.candidates(2, 1), 0b0011);
+        assert_eq!(sudoku.row_digits(2), 0b1100);
+        assert_eq!(sudoku.col_digits(0), 0b1001);
+        assert_eq!(sudoku.box_digits(3), 0b0100);
+
+        // Placing a digit removes it from the peers, removing it again restores it
+        sudoku.set(0, 1, 2);
+        assert_eq!(sudoku.row_digits(0), 0b0011);
+        assert_eq!(sudoku.candidates(0, 1), 0);
+        assert!(!sudoku.has_candidate(0, 2, 2));
+        assert!(!sudoku.has_candidate(3, 1, 2));
+        assert!(!sudoku.is_valid(1, 0, 2));
+        sudoku.set(0, 1, 0);
+        assert_eq!(sudoku.row_digits(0), 0b0001);
+        assert_eq!(sudoku.candidates(0, 1), 0b1110);
+        assert!(sudoku.has_candidate(0, 2, 2));
+        assert!(sudoku.is_valid(1, 0, 2));
+
+        // Overwriting a digit only restores it where no other digit rules it out
+        sudoku.set(1, 1, 4);
+        assert!(!sudoku.has_candidate(1, 3, 4));
+        sudoku.set(1, 1, 3);
+        assert!(sudoku.has_candidate(1, 3, 4));
+        assert!(!sudoku.has_candidate(3, 1, 4));
+        assert!(!sudoku.has_candidate(1, 3, 3));
+        sudoku.set(1, 1, 0);
+
+        // Eliminations are kept, unless the cell gains the digit back or they are reset
+        assert!(sudoku.eliminate(3, 3, 2));
+        assert!(!sudoku.eliminate(3, 3, 2));
+        sudoku.set(0, 1, 2);
+        sudoku.set(0, 1, 0);
+        assert!(!sudoku.has_candidate(3, 3, 2));
+        sudoku.set_candidates(3, 3, 0b1111);
+        assert_eq!(sudoku.candidates(3, 3), 0b1011);
+        sudoku.reset_candidates();
+        assert_eq!(sudoku.candidates(3, 3), 0b1011);
+        assert!(sudoku.eliminate(3, 3, 2));
+        sudoku.reset_candidates();
+        assert!(sudoku.has_candidate(3, 3, 2));
+
+        // Variants and regions are part of the candidates
+        sudoku.set_variants(&[Variant::Diagonal]);
+        assert!(!sudoku.has_candidate(3, 3, 1));
+        sudoku.set_variants(&[]);
+        assert!(!sudoku.has_candidate(1, 1, 1));
+        sudoku.set_regions(Some(Regions::try_from_str("1112122233343444")?))?;
+        assert_eq!(sudoku.box_digits(0), 0b0001);
+        assert!(!sudoku.has_candidate(1, 0, 1));
+        assert!(sudoku.has_candidate(1, 1, 1));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pencil_marks() -> Result<()> {
+        let mut sudoku = Sudoku::try_from_str("1.......4..3....")?;
+        sudoku.eliminate(0, 1, 3);
+        sudoku.eliminate(1, 1, 2);
+        sudoku.eliminate(1, 1, 3);
+        sudoku.set_candidates(3, 3, 0);
+        let pencil_marks = sudoku.pencil_marks();
+        assert_eq!(
+            pencil_marks,
+            concat!(
+                " 1    24   | 234  24\n",
+                " 23   [4]  | 1234 124\n",
+                "-----------+-----------\n",
+                " 4    12   | 12   3\n",
+                " 23   123  | 124  []\n",
+            )
+        );
+
+        let parsed = Sudoku::try_from_pencil_marks(&pencil_marks)?;
+        assert_eq!(parsed.serialize(), sudoku.serialize());
+        assert_eq!(parsed.pencil_marks(), pencil_marks);
+
+        // Plain cells get all candidates allowed by the placed digits, impossible ones are dropped
+        let parsed = Sudoku::try_from_pencil_marks("1 . 0 [4] . . . . . . . . 1234 . . .")?;
+        assert_eq!(parsed.get(0, 0), 1);
+        assert_eq!(parsed.candidates(0, 1), 0b1110);
+        assert_eq!(parsed.candidates(0, 3), 0b1000);
+        assert_eq!(parsed.candidates(3, 0), 0b1110);
+
+        assert!(Sudoku::try_from_pencil_marks("1 2 3").is_err());
+        assert!(Sudoku::try_from_pencil_marks("1 . . . . . . . . . . . . . . [5]").is_err());
+        assert!(Sudoku::try_from_pencil_marks("1 . . . . . . . . . . . . . . [12").is_err());
+
         Ok(())
     }
 }
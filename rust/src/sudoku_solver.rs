@@ -1,5 +1,170 @@
-use super::sudoku::Sudoku;
+use serde::Serialize;
+use std::fmt;
+
+use super::{
+    algorithm_x::{Interruption, SearchLimits, SearchMetrics},
+    sudoku::Sudoku,
+};
 
 pub trait SudokuSolver {
     fn solve(&self, board: Sudoku) -> Option<Sudoku>;
+
+    /// Solves the board like [`SudokuSolver::solve`], but gives up once one of the limits is
+    /// exceeded.
+    ///
+    /// By default the limits are only checked before solving, solvers which search override
+    /// this to check them while searching.
+    fn solve_with_limits(&self, board: Sudoku, limits: &SearchLimits) -> SolveOutcome {
+        if let Some(interruption) = limits.check(0, true) {
+            return interruption.into();
+        }
+        self.solve(board).into()
+    }
+
+    /// Counts the solutions of the given board, but stops as soon as `limit` solutions have
+    /// been found.
+    fn count_solutions(&self, board: &Sudoku, limit: usize) -> usize;
+
+    /// Checks whether the given board has exactly one solution.
+    fn is_unique(&self, board: &Sudoku) -> Uniqueness {
+        match self.count_solutions(board, 2) {
+            0 => Uniqueness::None,
+            1 => Uniqueness::Unique,
+            _ => Uniqueness::Multiple,
+        }
+    }
+
+    /// Solves the board like [`SudokuSolver::solve`], and also returns the size of the search
+    /// tree. Solvers which don't search return empty metrics.
+    fn solve_with_metrics(&self, board: Sudoku) -> (Option<Sudoku>, SearchMetrics) {
+        (self.solve(board), SearchMetrics::default())
+    }
+
+    /// Solves the board and counts its solutions like [`SudokuSolver::count_solutions`] at the
+    /// same time. Returns the first solution and the number of solutions, and stores the size
+    /// of the search tree in `metrics` if it is given.
+    ///
+    /// By default the board is counted and then solved again, solvers which search override
+    /// this to take the solution from the counting search.
+    fn solve_and_count(
+        &self,
+        board: Sudoku,
+        limit: usize,
+        metrics: Option<&mut SearchMetrics>,
+    ) -> (Option<Sudoku>, usize) {
+        let count = self.count_solutions(&board, limit);
+        let solution = match (count, metrics) {
+            (0, _) => None,
+            (_, Some(metrics)) => {
+                let (solution, solve_metrics) = self.solve_with_metrics(board);
+                *metrics = solve_metrics;
+                solution
+            }
+            (_, None) => self.solve(board),
+        };
+        (solution, count)
+    }
+
+    /// The name of a column in [`SearchMetrics::column_selections`].
+    fn column_name(&self, col: usize) -> String {
+        format!("column {}", col + 1)
+    }
+}
+
+/// Describes how many solutions a sudoku has.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Uniqueness {
+    Unique,
+    Multiple,
+    None,
+}
+
+/// The result of [`SudokuSolver::solve_with_limits`].
+// The solution isn't boxed, so that solving doesn't allocate any more than `solve` does
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug)]
+pub enum SolveOutcome {
+    Solved(Sudoku),
+    NoSolution,
+    /// The deadline passed before a solution was found
+    TimedOut,
+    /// The node budget was used up before a solution was found
+    NodeLimit,
+    /// The cancellation token was set before a solution was found
+    Cancelled,
+}
+
+impl SolveOutcome {
+    /// The solution, if one was found.
+    pub fn solution(self) -> Option<Sudoku> {
+        match self {
+            SolveOutcome::Solved(solution) => Some(solution),
+            _ => None,
+        }
+    }
+
+    /// Why the search was stopped, if it was.
+    pub fn interruption(&self) -> Option<Interruption> {
+        match self {
+            SolveOutcome::TimedOut => Some(Interruption::TimedOut),
+            SolveOutcome::NodeLimit => Some(Interruption::NodeLimit),
+            SolveOutcome::Cancelled => Some(Interruption::Cancelled),
+            SolveOutcome::Solved(_) | SolveOutcome::NoSolution => None,
+        }
+    }
+}
+
+impl From<Option<Sudoku>> for SolveOutcome {
+    fn from(solution: Option<Sudoku>) -> Self {
+        solution.map_or(SolveOutcome::NoSolution, SolveOutcome::Solved)
+    }
+}
+
+impl From<Interruption> for SolveOutcome {
+    fn from(interruption: Interruption) -> Self {
+        match interruption {
+            Interruption::TimedOut => SolveOutcome::TimedOut,
+            Interruption::NodeLimit => SolveOutcome::NodeLimit,
+            Interruption::Cancelled => SolveOutcome::Cancelled,
+        }
+    }
+}
+
+/// A step of a solver, for replaying how it searched for a solution. Rows and columns start at
+/// 1, and columns of the exact cover matrix are named like in the search statistics.
+///
+/// The backtracking solver has no columns to cover, and chooses cells instead of columns.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum TraceEvent {
+    /// The column or cell with the fewest options was chosen to branch on, `count` is the
+    /// number of options
+    Choose { column: String, count: usize },
+    /// A column was covered, removing all candidates which conflict with its constraint
+    Cover { column: String },
+    /// A digit was placed, as a guess or because it was the only option left
+    Place { row: usize, col: usize, digit: u8 },
+    /// A placed digit was taken back. The backtracking solver only takes back guesses, the
+    /// digits placed after a guess go with it.
+    Backtrack { row: usize, col: usize, digit: u8 },
+    /// All cells are filled
+    Solution,
+}
+
+impl fmt::Display for TraceEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TraceEvent::Choose { column, count } => {
+                write!(f, "choose {} with {} options", column, count)
+            }
+            TraceEvent::Cover { column } => write!(f, "cover {}", column),
+            TraceEvent::Place { row, col, digit } => {
+                write!(f, "place {} in r{}c{}", digit, row, col)
+            }
+            TraceEvent::Backtrack { row, col, digit } => {
+                write!(f, "take back {} from r{}c{}", digit, row, col)
+            }
+            TraceEvent::Solution => write!(f, "solution found"),
+        }
+    }
 }
@@ -0,0 +1,503 @@
+use crossterm::{
+    cursor::{Hide, MoveTo, Show},
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute, queue,
+    style::{Attribute, Color, ContentStyle, Print, PrintStyledContent, StyledContent},
+    terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use std::io::{self, Write};
+use sudoku_solver::{
+    game::{Game, Hint, SolutionPath},
+    sudoku::{BoardSize, Sudoku},
+    TraceEvent,
+};
+
+// The keys of every mode, shown below the board. Letters are digits on large boards, so the
+// commands use other keys or need the control key.
+const PLAY_KEYS: [&str; 2] = [
+    "arrows: move  digits: enter  0/del: clear  tab: pencil marks",
+    "?: hint  ^z/^y: undo/redo  ^p: solution path  ^q: quit",
+];
+const PATH_KEYS: [&str; 2] = [
+    "left/right: step  pgup/pgdn: 100 steps  home/end: start/end",
+    "^p/esc: back to the game  ^q: quit",
+];
+
+// A character of the board as it is drawn by its `Display` implementation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Glyph {
+    Cell { row: usize, col: usize },
+    Border(char),
+}
+
+// Splits the board as it is displayed into its cells and the borders between the boxes, so
+// the cells can be drawn in different colours
+fn layout(board: &Sudoku) -> Vec<Vec<Glyph>> {
+    let side = board.size().side();
+    let mut idx = 0;
+    board
+        .to_string()
+        .lines()
+        .map(|line| {
+            // Lines between the boxes only consist of dashes and crosses
+            let separator = line.chars().all(|c| c == '-' || c == '+');
+            line.chars()
+                .map(|c| match c {
+                    '|' => Glyph::Border(c),
+                    _ if separator => Glyph::Border(c),
+                    _ => {
+                        let glyph = Glyph::Cell {
+                            row: idx / side,
+                            col: idx % side,
+                        };
+                        idx += 1;
+                        glyph
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Play,
+    // Digits toggle the pencil marks instead of filling the cell
+    PencilMarks,
+    // Stepping through the solution path of the solver
+    Path,
+}
+
+pub struct App {
+    title: String,
+    game: Game,
+    path: SolutionPath,
+    mode: Mode,
+    message: String,
+    // The cells the last hint is about
+    highlighted: Vec<(usize, usize)>,
+    quit: bool,
+}
+
+impl App {
+    pub fn new(title: String, game: Game, path: SolutionPath) -> Self {
+        Self {
+            title,
+            game,
+            path,
+            mode: Mode::Play,
+            message: String::new(),
+            highlighted: Vec::new(),
+            quit: false,
+        }
+    }
+
+    // Updates the state for a key press, returns whether the screen has to be redrawn
+    fn handle_key(&mut self, key: KeyEvent) -> bool {
+        if key.kind != KeyEventKind::Press {
+            return false;
+        }
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        if control && matches!(key.code, KeyCode::Char('q' | 'c')) {
+            self.quit = true;
+            return false;
+        }
+        match self.mode {
+            Mode::Path => self.handle_path_key(key.code, control),
+            Mode::Play | Mode::PencilMarks => self.handle_play_key(key.code, control),
+        }
+        true
+    }
+
+    // The commands are matched before the digits, which they never overlap with
+    fn handle_play_key(&mut self, code: KeyCode, control: bool) {
+        match (code, control) {
+            (KeyCode::Up, _) => self.game.move_cursor(-1, 0),
+            (KeyCode::Down, _) => self.game.move_cursor(1, 0),
+            (KeyCode::Left, _) => self.game.move_cursor(0, -1),
+            (KeyCode::Right, _) => self.game.move_cursor(0, 1),
+            (KeyCode::Tab, _) => {
+                self.mode = match self.mode {
+                    Mode::Play => Mode::PencilMarks,
+                    _ => Mode::Play,
+                }
+            }
+            (KeyCode::Char('?'), _) => self.hint(),
+            (KeyCode::Char('z'), true) => {
+                if self.game.undo() {
+                    self.edited();
+                } else {
+                    self.message = "Nothing to undo.".to_string();
+                }
+            }
+            (KeyCode::Char('y'), true) => {
+                if self.game.redo() {
+                    self.edited();
+                } else {
+                    self.message = "Nothing to redo.".to_string();
+                }
+            }
+            (KeyCode::Char('p'), true) => {
+                self.mode = Mode::Path;
+                self.message.clear();
+            }
+            (KeyCode::Char(c), false) => {
+                if let Some(digit) = self.game.board().size().char_to_digit(c) {
+                    self.enter(digit);
+                }
+            }
+            (KeyCode::Backspace | KeyCode::Delete, _) => self.enter(0),
+            _ => {}
+        }
+    }
+
+    // Enters a digit into the cell at the cursor, or toggles its pencil mark
+    fn enter(&mut self, digit: u8) {
+        let changed = match self.mode {
+            Mode::PencilMarks if digit != 0 => self.game.toggle_mark(digit),
+            _ => self.game.enter(digit),
+        };
+        if changed {
+            self.edited();
+        }
+    }
+
+    fn handle_path_key(&mut self, code: KeyCode, control: bool) {
+        let step = self.path.step();
+        match code {
+            KeyCode::Right => {
+                self.path.forward();
+            }
+            KeyCode::Left => {
+                self.path.back();
+            }
+            KeyCode::PageDown => self.path.go_to(step + 100),
+            KeyCode::PageUp => self.path.go_to(step.saturating_sub(100)),
+            KeyCode::Home => self.path.go_to(0),
+            KeyCode::End => self.path.go_to(usize::MAX),
+            KeyCode::Char('p') if control => self.mode = Mode::Play,
+            KeyCode::Esc => self.mode = Mode::Play,
+            _ => {}
+        }
+    }
+
+    // Forgets the last hint after the board changed
+    fn edited(&mut self) {
+        self.highlighted.clear();
+        self.message.clear();
+        if self.game.is_solved() {
+            self.message = "Solved!".to_string();
+        }
+    }
+
+    fn hint(&mut self) {
+        let (message, highlighted) = match self.game.hint() {
+            Hint::Mistake { row, col, digit } => (
+                format!("r{}c{} can't be {}.", row + 1, col + 1, digit),
+                vec![(row, col)],
+            ),
+            Hint::Step(step) => {
+                let cells = step
+                    .cells
+                    .iter()
+                    .copied()
+                    .chain(step.placements.iter().map(|&(row, col, _)| (row, col)))
+                    .chain(step.eliminations.iter().map(|&(row, col, _)| (row, col)))
+                    .collect();
+                (step.to_string(), cells)
+            }
+            Hint::Reveal { row, col, digit } => (
+                format!(
+                    "No technique applies, r{}c{} is {}.",
+                    row + 1,
+                    col + 1,
+                    digit
+                ),
+                vec![(row, col)],
+            ),
+            Hint::Solved => ("Solved!".to_string(), Vec::new()),
+            Hint::NoSolution => ("The sudoku has no solution.".to_string(), Vec::new()),
+        };
+        self.message = message;
+        self.highlighted = highlighted;
+    }
+
+    // The style of a cell on the board of the game
+    fn play_style(&self, row: usize, col: usize, conflicts: &[(usize, usize)]) -> ContentStyle {
+        let mut style = ContentStyle::new();
+        if self.game.is_given(row, col) {
+            style.attributes.set(Attribute::Bold);
+        } else if self.game.board().get(row, col) != 0 {
+            style.foreground_color = Some(Color::Cyan);
+        } else if self.game.marks(row, col) != 0 {
+            style.foreground_color = Some(Color::Yellow);
+        } else {
+            style.foreground_color = Some(Color::DarkGrey);
+        }
+        if conflicts.contains(&(row, col)) {
+            style.foreground_color = Some(Color::Red);
+        }
+        if self.highlighted.contains(&(row, col)) {
+            style.background_color = Some(Color::DarkGreen);
+        }
+        if self.game.cursor() == (row, col) {
+            style.attributes.set(Attribute::Reverse);
+        }
+        style
+    }
+
+    // The style of a cell on the board of the solution path
+    fn path_style(&self, row: usize, col: usize) -> ContentStyle {
+        let mut style = ContentStyle::new();
+        if self.game.is_given(row, col) {
+            style.attributes.set(Attribute::Bold);
+        } else if self.path.board().get(row, col) != 0 {
+            style.foreground_color = Some(Color::Cyan);
+        } else {
+            style.foreground_color = Some(Color::DarkGrey);
+        }
+        match self.path.current() {
+            Some(&TraceEvent::Place { row: r, col: c, .. }) if (r - 1, c - 1) == (row, col) => {
+                style.attributes.set(Attribute::Reverse);
+            }
+            Some(&TraceEvent::Backtrack { row: r, col: c, .. }) if (r - 1, c - 1) == (row, col) => {
+                style.foreground_color = Some(Color::Red);
+                style.attributes.set(Attribute::Reverse);
+            }
+            _ => {}
+        }
+        style
+    }
+
+    // The lines below the board
+    fn status(&self) -> Vec<String> {
+        match self.mode {
+            Mode::Path => {
+                let event = self
+                    .path
+                    .current()
+                    .map_or_else(|| "start".to_string(), |event| event.to_string());
+                vec![
+                    format!(
+                        "Solution path, step {}/{}: {}",
+                        self.path.step(),
+                        self.path.events().len(),
+                        event
+                    ),
+                    String::new(),
+                    PATH_KEYS[0].to_string(),
+                    PATH_KEYS[1].to_string(),
+                ]
+            }
+            Mode::Play | Mode::PencilMarks => {
+                let (row, col) = self.game.cursor();
+                let marks = self.game.marks(row, col);
+                let marks = (1..=self.game.board().size().side() as u8)
+                    .filter(|digit| marks & (1 << (digit - 1)) != 0)
+                    .map(|digit| BoardSize::digit_to_char(digit).to_string())
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                let mode = match self.mode {
+                    Mode::PencilMarks => " (entering pencil marks)",
+                    _ => "",
+                };
+                vec![
+                    format!("r{}c{}, marks: {}{}", row + 1, col + 1, marks, mode),
+                    self.message.clone(),
+                    PLAY_KEYS[0].to_string(),
+                    PLAY_KEYS[1].to_string(),
+                ]
+            }
+        }
+    }
+
+    fn draw(&self, out: &mut impl Write) -> io::Result<()> {
+        let board = match self.mode {
+            Mode::Path => self.path.board(),
+            Mode::Play | Mode::PencilMarks => self.game.board(),
+        };
+        let conflicts = match self.mode {
+            Mode::Path => Vec::new(),
+            Mode::Play | Mode::PencilMarks => self.game.conflicts(),
+        };
+
+        queue!(out, Clear(ClearType::All), MoveTo(0, 0), Print(&self.title))?;
+        let mut y = 2;
+        for line in layout(board) {
+            queue!(out, MoveTo(0, y))?;
+            for glyph in line {
+                let content = match glyph {
+                    Glyph::Border(c) => StyledContent::new(
+                        ContentStyle {
+                            foreground_color: Some(Color::DarkGrey),
+                            ..ContentStyle::new()
+                        },
+                        c,
+                    ),
+                    Glyph::Cell { row, col } => {
+                        let style = match self.mode {
+                            Mode::Path => self.path_style(row, col),
+                            Mode::Play | Mode::PencilMarks => self.play_style(row, col, &conflicts),
+                        };
+                        StyledContent::new(style, BoardSize::digit_to_char(board.get(row, col)))
+                    }
+                };
+                queue!(out, PrintStyledContent(content))?;
+            }
+            y += 1;
+        }
+        for line in self.status() {
+            y += 1;
+            queue!(out, MoveTo(0, y), Print(line))?;
+        }
+        out.flush()
+    }
+}
+
+// Switches the terminal to raw mode on an alternate screen, and back once it is dropped,
+// also when returning early with an error
+struct RawTerminal;
+
+impl RawTerminal {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), EnterAlternateScreen, Hide)?;
+        Ok(RawTerminal)
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Runs the app until it is quit.
+pub fn run(mut app: App) -> io::Result<()> {
+    let _terminal = RawTerminal::enter()?;
+    let mut out = io::stdout();
+    app.draw(&mut out)?;
+    while !app.quit {
+        let redraw = match event::read()? {
+            Event::Key(key) => app.handle_key(key),
+            Event::Resize(_, _) => true,
+            _ => false,
+        };
+        if redraw {
+            app.draw(&mut out)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sudoku_solver::algorithm_x_solver::AlgorithmXSudokuSolver;
+
+    const PUZZLE: &str =
+        "4.....8.5.3..........7......2.....6.....8.4......1.......6.3.7.5..2.....1.4......";
+
+    #[test]
+    fn test_layout() {
+        let board = Sudoku::try_from_str(PUZZLE).unwrap();
+        let layout = layout(&board);
+        assert_eq!(layout.len(), 11);
+        assert!(layout.iter().all(|line| line.len() == 11));
+        assert_eq!(
+            layout[3],
+            vec![Glyph::Border('-'); 3]
+                .into_iter()
+                .chain([Glyph::Border('+')])
+                .cycle()
+                .take(11)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(layout[4][3], Glyph::Border('|'));
+
+        // The cells are in row major order
+        let cells: Vec<_> = layout
+            .iter()
+            .flatten()
+            .filter_map(|glyph| match *glyph {
+                Glyph::Cell { row, col } => Some((row, col)),
+                Glyph::Border(_) => None,
+            })
+            .collect();
+        let expected: Vec<_> = (0..9)
+            .flat_map(|row| (0..9).map(move |col| (row, col)))
+            .collect();
+        assert_eq!(cells, expected);
+    }
+
+    #[test]
+    fn test_keys() {
+        let sudoku = Sudoku::try_from_str(PUZZLE).unwrap();
+        let mut events = Vec::new();
+        let solution = AlgorithmXSudokuSolver::new()
+            .trace(sudoku.clone(), &mut events)
+            .solution();
+        let game = Game::new(sudoku.clone(), solution);
+        let mut app = App::new(String::new(), game, SolutionPath::new(sudoku, events));
+        let press = |app: &mut App, code| app.handle_key(KeyEvent::from(code));
+        let control = |app: &mut App, c| {
+            app.handle_key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL))
+        };
+
+        press(&mut app, KeyCode::Right);
+        press(&mut app, KeyCode::Char('5'));
+        assert_eq!(app.game.board().get(0, 1), 5);
+        press(&mut app, KeyCode::Tab);
+        press(&mut app, KeyCode::Right);
+        press(&mut app, KeyCode::Char('2'));
+        assert_eq!(app.game.marks(0, 2), 0b10);
+        control(&mut app, 'z');
+        control(&mut app, 'z');
+        assert_eq!(app.game.board().get(0, 1), 0);
+        assert_eq!(app.game.marks(0, 2), 0);
+        control(&mut app, 'y');
+        assert_eq!(app.game.board().get(0, 1), 5);
+
+        press(&mut app, KeyCode::Char('?'));
+        assert!(!app.message.is_empty());
+        assert!(!app.highlighted.is_empty());
+
+        control(&mut app, 'p');
+        press(&mut app, KeyCode::End);
+        assert!(app.path.board().is_solved());
+        assert!(app.status()[0].ends_with("solution found"));
+        press(&mut app, KeyCode::Esc);
+        assert_eq!(app.mode, Mode::Play);
+
+        press(&mut app, KeyCode::Char('q'));
+        assert!(!app.quit);
+        control(&mut app, 'q');
+        assert!(app.quit);
+    }
+
+    #[test]
+    fn test_keys_on_large_boards() {
+        // On a 25x25 board the letters up to p are digits, 'h' is 17
+        let sudoku = Sudoku::with_size(BoardSize::new(5, 5).unwrap());
+        let game = Game::new(sudoku.clone(), None);
+        let mut app = App::new(String::new(), game, SolutionPath::new(sudoku, Vec::new()));
+        let press = |app: &mut App, code| app.handle_key(KeyEvent::from(code));
+
+        for (col, (c, digit)) in [('h', 17), ('m', 22), ('p', 25)].into_iter().enumerate() {
+            press(&mut app, KeyCode::Char(c));
+            assert_eq!(app.game.board().get(0, col), digit);
+            press(&mut app, KeyCode::Right);
+        }
+        assert_eq!(app.mode, Mode::Play);
+        // 'u' is too large for a digit, and doesn't undo either
+        press(&mut app, KeyCode::Char('u'));
+        assert_eq!(app.game.board().get(0, 2), 25);
+
+        press(&mut app, KeyCode::Tab);
+        press(&mut app, KeyCode::Char('H'));
+        assert_eq!(app.game.marks(0, 3), 1 << 16);
+        app.handle_key(KeyEvent::new(KeyCode::Char('p'), KeyModifiers::CONTROL));
+        assert_eq!(app.mode, Mode::Path);
+    }
+}
@@ -0,0 +1,247 @@
+use std::sync::{Arc, Mutex, PoisonError, Weak};
+
+use crate::{
+    regions::Regions,
+    sudoku::{BoardSize, Variant},
+};
+
+/// The units and peers of every cell of a board layout, given by its size, variant rules and
+/// regions. Cells are numbered in row major order.
+///
+/// The tables only depend on the layout, so they are built once and shared by all sudokus
+/// and solvers with the same layout, see [`Units::get`].
+#[derive(Debug, Clone)]
+pub(crate) struct Units {
+    size: BoardSize,
+    variants: Vec<Variant>,
+    regions: Option<Regions>,
+    // Rows, columns, boxes or regions and variant units, in that order
+    units: Vec<Vec<usize>>,
+    // The indices of the units every cell belongs to
+    cell_units: Vec<Vec<usize>>,
+    // Groups of cells which can't hold a digit twice, but don't have to hold every digit like
+    // the cages of a killer sudoku
+    groups: Vec<Vec<usize>>,
+    // The cells which can't hold the same digit as a cell because of a variant rule or a group,
+    // without sharing a unit with it
+    neighbours: Vec<Vec<usize>>,
+    // The cells sharing a unit with every cell or seeing it through a variant, sorted
+    peers: Vec<Vec<usize>>,
+}
+
+// The tables of the layouts in use, entries are dropped with the last sudoku using them
+static CACHE: Mutex<Vec<Weak<Units>>> = Mutex::new(Vec::new());
+
+impl Units {
+    /// The tables of the given layout, taken from the sudokus and solvers still using them
+    /// or built if there are none. The variants have to be sorted and deduplicated.
+    pub(crate) fn get(
+        size: BoardSize,
+        variants: &[Variant],
+        regions: Option<&Regions>,
+    ) -> Arc<Units> {
+        let mut cache = CACHE.lock().unwrap_or_else(PoisonError::into_inner);
+        cache.retain(|units| units.strong_count() > 0);
+        let cached = cache
+            .iter()
+            .filter_map(Weak::upgrade)
+            .find(|units| units.is_layout(size, variants, regions));
+        if let Some(units) = cached {
+            return units;
+        }
+        let units = Arc::new(Units::build(size, variants, regions.cloned()));
+        cache.push(Arc::downgrade(&units));
+        units
+    }
+
+    fn build(size: BoardSize, variants: &[Variant], regions: Option<Regions>) -> Units {
+        let side = size.side();
+        let n_cells = size.n_cells();
+
+        let rows = (0..side).map(|row| (0..side).map(|i| row * side + i).collect());
+        let cols = (0..side).map(|col| (0..side).map(|i| i * side + col).collect());
+        let box_regions = regions.clone().unwrap_or_else(|| Regions::boxes(size));
+        let boxes = (0..side).map(|box_| {
+            box_regions
+                .cells(box_)
+                .iter()
+                .map(|&(row, col)| row * side + col)
+                .collect()
+        });
+        let variant_units = variants
+            .iter()
+            .flat_map(|variant| variant.units(size))
+            .map(|unit| unit.iter().map(|&(row, col)| row * side + col).collect());
+        let units: Vec<Vec<usize>> = rows.chain(cols).chain(boxes).chain(variant_units).collect();
+
+        let mut cell_units = vec![Vec::new(); n_cells];
+        for (unit_idx, unit) in units.iter().enumerate() {
+            for &idx in unit {
+                cell_units[idx].push(unit_idx);
+            }
+        }
+
+        let mut neighbours = Vec::with_capacity(n_cells);
+        let mut peers = Vec::with_capacity(n_cells);
+        for (idx, own_units) in cell_units.iter().enumerate() {
+            let mut unit_peers: Vec<usize> = own_units
+                .iter()
+                .flat_map(|&unit_idx| units[unit_idx].iter().copied())
+                .filter(|&other| other != idx)
+                .collect();
+            unit_peers.sort_unstable();
+            unit_peers.dedup();
+
+            let mut cell_neighbours: Vec<usize> = variants
+                .iter()
+                .flat_map(|variant| variant.neighbours(size, idx / side, idx % side))
+                .map(|(row, col)| row * side + col)
+                .filter(|other| unit_peers.binary_search(other).is_err())
+                .collect();
+            cell_neighbours.sort_unstable();
+            cell_neighbours.dedup();
+
+            let mut cell_peers = unit_peers;
+            cell_peers.extend_from_slice(&cell_neighbours);
+            cell_peers.sort_unstable();
+            neighbours.push(cell_neighbours);
+            peers.push(cell_peers);
+        }
+
+        Units {
+            size,
+            variants: variants.to_vec(),
+            regions,
+            units,
+            cell_units,
+            groups: Vec::new(),
+            neighbours,
+            peers,
+        }
+    }
+
+    /// The tables with additional groups of cells which can't hold a digit twice, like the
+    /// cages of a killer sudoku. They aren't shared with other boards.
+    pub(crate) fn with_groups(&self, groups: &[Vec<usize>]) -> Units {
+        let mut units = self.clone();
+        for group in groups {
+            for &idx in group {
+                let others = group
+                    .iter()
+                    .filter(|&&other| other != idx && !self.sees(idx, other));
+                units.neighbours[idx].extend(others.clone());
+                units.peers[idx].extend(others);
+            }
+        }
+        for cells in units.neighbours.iter_mut().chain(&mut units.peers) {
+            cells.sort_unstable();
+            cells.dedup();
+        }
+        units.groups.extend_from_slice(groups);
+        units
+    }
+
+    // Checks whether the tables were built for the given layout without groups
+    fn is_layout(&self, size: BoardSize, variants: &[Variant], regions: Option<&Regions>) -> bool {
+        self.size == size
+            && self.variants == variants
+            && self.regions.as_ref() == regions
+            && self.groups.is_empty()
+    }
+
+    /// Checks whether both tables are for the same layout, which is usually the same table.
+    pub(crate) fn same_layout(&self, other: &Units) -> bool {
+        std::ptr::eq(self, other)
+            || (other.is_layout(self.size, &self.variants, self.regions.as_ref())
+                && self.groups == other.groups)
+    }
+
+    pub(crate) fn size(&self) -> BoardSize {
+        self.size
+    }
+
+    pub(crate) fn variants(&self) -> &[Variant] {
+        &self.variants
+    }
+
+    pub(crate) fn regions(&self) -> Option<&Regions> {
+        self.regions.as_ref()
+    }
+
+    /// All units, the rows come first, then the columns, boxes and variant units
+    pub(crate) fn units(&self) -> &[Vec<usize>] {
+        &self.units
+    }
+
+    /// The indices of the units a cell belongs to, its row, column and box come first
+    pub(crate) fn cell_units(&self, idx: usize) -> &[usize] {
+        &self.cell_units[idx]
+    }
+
+    /// The box, or region, a cell belongs to
+    pub(crate) fn box_idx(&self, idx: usize) -> usize {
+        self.cell_units[idx][2] - 2 * self.size.side()
+    }
+
+    /// The cells which can't hold the same digit as a cell only because of a variant like
+    /// anti-knight or a group, not because they share a unit with it
+    pub(crate) fn neighbours(&self, idx: usize) -> &[usize] {
+        &self.neighbours[idx]
+    }
+
+    /// The cells which can't hold the same digit as a cell, sorted
+    pub(crate) fn peers(&self, idx: usize) -> &[usize] {
+        &self.peers[idx]
+    }
+
+    /// Checks whether two cells can't hold the same digit
+    pub(crate) fn sees(&self, a: usize, b: usize) -> bool {
+        self.peers[a].binary_search(&b).is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_units() {
+        let units = Units::get(BoardSize::CLASSIC, &[], None);
+        assert_eq!(units.units().len(), 27);
+        assert_eq!(units.cell_units(40), [4, 13, 22]);
+        assert_eq!(units.box_idx(80), 8);
+        assert_eq!(units.peers(0).len(), 20);
+        assert!(units.sees(0, 80 - 8) && units.sees(0, 20) && !units.sees(0, 21));
+        assert!(units.neighbours(0).is_empty());
+
+        let anti_knight = Units::get(BoardSize::CLASSIC, &[Variant::AntiKnight], None);
+        // r2c1 and r3c2 are a knight's move from r1c3 as well, but in the same box
+        assert_eq!(anti_knight.neighbours(2), [9 + 4, 18 + 3]);
+        assert!(anti_knight.sees(2, 9) && anti_knight.sees(2, 13));
+
+        let diagonal = Units::get(BoardSize::CLASSIC, &[Variant::Diagonal], None);
+        assert_eq!(diagonal.units().len(), 29);
+        assert_eq!(diagonal.cell_units(40), [4, 13, 22, 27, 28]);
+        assert_eq!(diagonal.peers(0).len(), 20 + 6);
+    }
+
+    #[test]
+    fn test_sharing() {
+        let units = Units::get(BoardSize::new(2, 3).unwrap(), &[], None);
+        let again = Units::get(BoardSize::new(2, 3).unwrap(), &[], None);
+        assert!(Arc::ptr_eq(&units, &again));
+        assert!(units.same_layout(&again));
+
+        let regions = Regions::boxes(BoardSize::new(2, 3).unwrap());
+        let jigsaw = Units::get(BoardSize::new(2, 3).unwrap(), &[], Some(&regions));
+        assert!(!Arc::ptr_eq(&units, &jigsaw));
+        assert!(!units.same_layout(&jigsaw));
+
+        // A group adds the cells which don't already share a unit to the peers
+        let caged = units.with_groups(&[vec![0, 1, 10]]);
+        assert!(!units.sees(0, 10) && caged.sees(0, 10) && caged.sees(10, 1));
+        assert_eq!(caged.neighbours(1), [10]);
+        assert_eq!(caged.peers(0).len(), units.peers(0).len() + 1);
+        assert!(!units.same_layout(&caged));
+    }
+}
@@ -0,0 +1,51 @@
+// Checks that searches don't allocate. The counting allocator replaces the global allocator of
+// this test binary only, the tests of the library keep the system allocator.
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use sudoku_solver::algorithm_x_solver::AlgorithmXWorkspaceSolver;
+use sudoku_solver::sudoku::Sudoku;
+use sudoku_solver::SudokuSolver;
+
+// Counts the allocations of every thread, so that tests running in parallel don't interfere
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|allocations| allocations.set(allocations.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations() -> usize {
+    ALLOCATIONS.with(Cell::get)
+}
+
+#[test]
+fn test_workspace_solver_allocations() {
+    let workspace_solver = AlgorithmXWorkspaceSolver::default();
+    let sudoku = Sudoku::try_from_str(
+        "4.....8.5.3..........7......2.....6.....8.4......1.......6.3.7.5..2.....1.4......",
+    )
+    .unwrap();
+    // The first search grows the stack of the matrix, the following ones reuse it
+    workspace_solver.solve(sudoku.clone()).unwrap();
+
+    let board = sudoku.clone();
+    let before = allocations();
+    let solution = workspace_solver.solve(board).unwrap();
+    let count = workspace_solver.count_solutions(&sudoku, 2);
+    assert_eq!(allocations(), before);
+    assert!(solution.is_solved());
+    assert_eq!(count, 1);
+}